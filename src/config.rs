@@ -1,39 +1,765 @@
+use chrono_tz::Tz;
+use ipnet::IpNet;
+use jsonwebtoken::Algorithm;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::env;
+use std::fmt::Display;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use thiserror::Error;
+use tower_cookies::cookie::SameSite;
+
+use crate::middleware::cors::OriginPattern;
+use crate::utils::session_cookie::parse_same_site;
+use axum::http::{HeaderName, Method};
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub database_url: String,
     pub redis_url: String,
-    pub jwt_secret: String,
+    /// Only required with `JWT_ALGORITHM=HS256`
+    pub jwt_secret: Option<String>,
+    pub jwt_algorithm: Algorithm,
+    pub jwt_key_rotation_hours: u64,
+    pub jwt_key_refresh_seconds: u64,
+    /// Lifetime of session tokens, also used as the session cookie's Max-Age
+    pub token_ttl_minutes: i64,
+    /// Address the HTTP server binds to
+    pub host: IpAddr,
     pub port: u16,
+    /// Reverse proxies whose `X-Forwarded-For` entries are believed
+    pub trusted_proxies: Vec<IpNet>,
+    pub db_max_connections: u32,
+    pub db_min_connections: u32,
+    /// How long a query waits for a free pool connection
+    pub db_acquire_timeout_seconds: u64,
+    /// Timeout of outgoing HTTP calls, e.g. to OIDC providers
+    pub http_client_timeout_seconds: u64,
+    pub webauthn_rp_id: String,
+    pub webauthn_rp_origin: String,
+    pub oidc_providers: Vec<OidcProviderConfig>,
+    pub session_cookie_secure: bool,
+    pub session_cookie_same_site: SameSite,
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+    /// Server-side secret mixed into password hashes; never stored in the database
+    pub password_pepper: Option<String>,
+    pub password_min_length: usize,
+    /// Minimum zxcvbn score (0-4) for new passwords
+    pub password_min_score: u8,
+    /// Local breached-password range files, see `utils::password_policy`
+    pub breached_passwords_dir: Option<PathBuf>,
+    /// Zone timestamps are rendered in for users without a preference
+    pub default_time_zone: Tz,
+    /// Per-dependency timeout of the readiness probe
+    pub health_check_timeout_ms: u64,
+    pub redis_failure_policy: RedisFailurePolicy,
+    /// How often a Redis outage is re-checked and pending revocations replayed
+    pub redis_reconnect_interval_seconds: u64,
+    /// Timeout for establishing the shared Redis connection
+    pub redis_connect_timeout_ms: u64,
+    /// Timeout for a single Redis command
+    pub redis_response_timeout_ms: u64,
+    /// Bearer token required by `/metrics`; open when unset
+    pub metrics_token: Option<String>,
+    /// Base URL of the OTLP/HTTP collector; trace export is off when unset
+    pub otlp_endpoint: Option<String>,
+    pub otel_service_name: String,
+    /// Share of new traces that are sampled; requests with a sampled
+    /// `traceparent` follow the caller's decision
+    pub otel_traces_sample_ratio: f64,
+    pub log_format: LogFormat,
+    /// Native HTTPS; `None` serves plain HTTP (e.g. behind a reverse proxy)
+    pub tls: Option<TlsConfig>,
+    /// Time allowed after SIGTERM/SIGINT for requests and background jobs to finish
+    pub shutdown_timeout_seconds: u64,
+    /// Origins allowed to make credentialed cross-origin requests
+    pub cors_allowed_origins: Vec<OriginPattern>,
+    pub cors_allowed_methods: Vec<Method>,
+    pub cors_allowed_headers: Vec<HeaderName>,
+    /// How long browsers may cache a preflight response
+    pub cors_max_age_seconds: u64,
+    /// Development mode: any origin is allowed, as if it were listed
+    pub cors_permissive: bool,
+}
+
+/// Methods and headers the API uses, allowed by default
+const DEFAULT_CORS_METHODS: &str = "GET,POST,PUT,PATCH,DELETE,OPTIONS";
+const DEFAULT_CORS_HEADERS: &str =
+    "authorization,content-type,x-csrf-token,accept-timezone,x-request-id";
+
+/// Output format of the log lines on stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+impl Display for LogFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        })
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What authentication does when the token blacklist in Redis cannot be checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedisFailurePolicy {
+    /// Accept tokens unless this instance knows they were revoked
+    Open,
+    /// Reject requests with `503`
+    Closed,
+}
+
+impl Display for RedisFailurePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RedisFailurePolicy::Open => "open",
+            RedisFailurePolicy::Closed => "closed",
+        })
+    }
+}
+
+impl FromStr for RedisFailurePolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "open" => Ok(RedisFailurePolicy::Open),
+            "closed" => Ok(RedisFailurePolicy::Closed),
+            _ => Err(()),
+        }
+    }
+}
+
+/// An external OpenID Connect identity provider, configured through
+/// `OIDC_PROVIDERS=<name>[,<name>...]` and `OIDC_<NAME>_*` settings
+/// (`[oidc.<name>]` in the config file).
+#[derive(Debug, Clone)]
+pub struct OidcProviderConfig {
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_url: String,
+    pub scopes: String,
+}
+
+/// HTTPS termination, enabled by setting both `TLS_CERT_PATH` and `TLS_KEY_PATH`.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// PEM certificate chain, leaf first
+    pub cert_path: PathBuf,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+    pub key_path: PathBuf,
+    /// How often the files are checked for changes
+    pub reload_interval_seconds: u64,
+    /// Plain HTTP port that redirects to HTTPS
+    pub redirect_port: Option<u16>,
 }
 
 #[derive(Error, Debug)]
 pub enum ConfigError {
-    #[error("missing env var: {0}")]
+    #[error("missing setting: {0} (set the env var, {0}_FILE, or the key in the config file)")]
     MissingEnv(String),
     #[error("invalid port: {0}")]
     InvalidPort(String),
+    #[error("invalid value for {0}: {1}")]
+    InvalidValue(String, String),
+    #[error("config file {0}: {1}")]
+    File(PathBuf, String),
+    #[error("cannot read {0} from {1}: {2}")]
+    SecretFile(String, PathBuf, std::io::Error),
+    #[error("both {0} and {0}_FILE are set in the {1}; use only one")]
+    Ambiguous(String, &'static str),
+    #[error("unknown settings in config file {0}: {1}")]
+    UnknownKeys(PathBuf, String),
+}
+
+/// Where a setting's effective value came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Env,
+    File,
+    Default,
+    Unset,
+}
+
+/// One resolved setting, as shown by `--print-config`.
+#[derive(Debug, Clone)]
+pub struct Setting {
+    pub key: String,
+    pub value: Option<String>,
+    pub origin: Origin,
+}
+
+/// Settings whose values are never printed
+const SECRET_KEYS: &[&str] = &["JWT_SECRET", "PASSWORD_PEPPER", "METRICS_TOKEN"];
+/// Settings that may carry a password in their URL
+const URL_KEYS: &[&str] = &["DATABASE_URL", "REDIS_URL"];
+
+/// Layered lookup of settings by their env var name, highest precedence first:
+/// the environment, the TOML config file, then the default given by the caller.
+///
+/// Every key `K` can also be set as `K_FILE`, naming a file whose contents
+/// are the value (e.g. a mounted secret). Empty values count as unset.
+struct Sources {
+    /// Config file keys flattened to env var names: `[redis] url` is `REDIS_URL`
+    file: HashMap<String, String>,
+    path: Option<PathBuf>,
+    used: RefCell<HashSet<String>>,
+    resolved: RefCell<Vec<Setting>>,
+}
+
+impl Sources {
+    fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let mut file = HashMap::new();
+        if let Some(path) = path {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| ConfigError::File(path.into(), e.to_string()))?;
+            let table: toml::Table = text
+                .parse()
+                .map_err(|e: toml::de::Error| ConfigError::File(path.into(), e.to_string()))?;
+            flatten_toml(path, "", table, &mut file)?;
+        }
+        Ok(Sources {
+            file,
+            path: path.map(Path::to_path_buf),
+            used: RefCell::default(),
+            resolved: RefCell::default(),
+        })
+    }
+
+    fn lookup(&self, key: &str) -> Result<Option<(String, Origin)>, ConfigError> {
+        let file_key = format!("{}_FILE", key);
+        self.used
+            .borrow_mut()
+            .extend([key.to_string(), file_key.clone()]);
+        let non_empty = |v: Option<String>| v.filter(|v| !v.is_empty());
+
+        let layers = [
+            (
+                non_empty(env::var(key).ok()),
+                non_empty(env::var(&file_key).ok()),
+                Origin::Env,
+                "environment",
+            ),
+            (
+                non_empty(self.file.get(key).cloned()),
+                non_empty(self.file.get(&file_key).cloned()),
+                Origin::File,
+                "config file",
+            ),
+        ];
+        for (value, value_file, origin, layer) in layers {
+            match (value, value_file) {
+                (Some(_), Some(_)) => return Err(ConfigError::Ambiguous(key.to_string(), layer)),
+                (Some(value), None) => return Ok(Some((value, origin))),
+                (None, Some(secret_path)) => {
+                    let secret_path = PathBuf::from(secret_path);
+                    let value = std::fs::read_to_string(&secret_path)
+                        .map_err(|e| ConfigError::SecretFile(key.to_string(), secret_path, e))?;
+                    // Files written by editors or `echo` end with a newline
+                    return Ok(
+                        non_empty(Some(value.trim_end_matches(['\r', '\n']).to_string()))
+                            .map(|v| (v, origin)),
+                    );
+                }
+                (None, None) => {}
+            }
+        }
+        Ok(None)
+    }
+
+    fn record(&self, key: &str, value: Option<String>, origin: Origin) {
+        self.resolved.borrow_mut().push(Setting {
+            key: key.to_string(),
+            value,
+            origin,
+        });
+    }
+
+    /// The value if set; recorded as unset otherwise.
+    fn get(&self, key: &str) -> Result<Option<String>, ConfigError> {
+        let found = self.lookup(key)?;
+        match &found {
+            Some((value, origin)) => self.record(key, Some(value.clone()), *origin),
+            None => self.record(key, None, Origin::Unset),
+        }
+        Ok(found.map(|(value, _)| value))
+    }
+
+    fn required(&self, key: &str) -> Result<String, ConfigError> {
+        self.get(key)?
+            .ok_or_else(|| ConfigError::MissingEnv(key.into()))
+    }
+
+    fn string_or(&self, key: &str, default: impl Into<String>) -> Result<String, ConfigError> {
+        self.parse(key, default.into())
+    }
+
+    fn parse<T: FromStr + Display>(&self, key: &str, default: T) -> Result<T, ConfigError> {
+        match self.lookup(key)? {
+            Some((v, origin)) => {
+                let parsed = v
+                    .trim()
+                    .parse::<T>()
+                    .map_err(|_| ConfigError::InvalidValue(key.into(), v.clone()))?;
+                self.record(key, Some(v), origin);
+                Ok(parsed)
+            }
+            None => {
+                self.record(key, Some(default.to_string()), Origin::Default);
+                Ok(default)
+            }
+        }
+    }
+
+    /// Config file keys that no setting asked for, most likely typos.
+    fn check_unused(&self) -> Result<(), ConfigError> {
+        let used = self.used.borrow();
+        let mut unknown: Vec<&str> = self
+            .file
+            .keys()
+            .filter(|k| !used.contains(*k))
+            .map(String::as_str)
+            .collect();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        unknown.sort_unstable();
+        let path = self.path.clone().unwrap_or_default();
+        Err(ConfigError::UnknownKeys(
+            path,
+            unknown.join(", ").to_lowercase(),
+        ))
+    }
+}
+
+/// Parses a comma-separated list, naming the first invalid entry on error.
+fn parse_list<T>(
+    key: &str,
+    value: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Vec<T>, ConfigError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| parse(item).ok_or_else(|| ConfigError::InvalidValue(key.into(), item.into())))
+        .collect()
+}
+
+/// Maps nested tables to env var style names; arrays become comma-separated lists.
+fn flatten_toml(
+    path: &Path,
+    prefix: &str,
+    table: toml::Table,
+    out: &mut HashMap<String, String>,
+) -> Result<(), ConfigError> {
+    for (name, value) in table {
+        let key = format!("{}{}", prefix, name.to_uppercase().replace('-', "_"));
+        let scalar = |value: toml::Value| match value {
+            toml::Value::String(s) => Ok(s),
+            toml::Value::Integer(_)
+            | toml::Value::Float(_)
+            | toml::Value::Boolean(_)
+            | toml::Value::Datetime(_) => Ok(value.to_string()),
+            _ => Err(ConfigError::File(
+                path.into(),
+                format!("unsupported value for {}", name),
+            )),
+        };
+        match value {
+            toml::Value::Table(nested) => flatten_toml(path, &format!("{}_", key), nested, out)?,
+            toml::Value::Array(items) => {
+                let items = items
+                    .into_iter()
+                    .map(scalar)
+                    .collect::<Result<Vec<_>, _>>()?;
+                out.insert(key, items.join(","));
+            }
+            other => {
+                out.insert(key, scalar(other)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Renders settings as a TOML config file, with secrets and URL passwords redacted.
+pub fn render_settings(settings: &[Setting]) -> String {
+    let mut out = String::from(
+        "# Effective configuration; precedence: environment > config file > default\n",
+    );
+    for setting in settings {
+        let key = setting.key.to_lowercase();
+        let Some(value) = &setting.value else {
+            out.push_str(&format!("# {} is not set\n", key));
+            continue;
+        };
+        let value = if SECRET_KEYS.contains(&setting.key.as_str())
+            || setting.key.ends_with("_CLIENT_SECRET")
+        {
+            "<redacted>".to_string()
+        } else if URL_KEYS.contains(&setting.key.as_str()) {
+            redact_url_password(value)
+        } else {
+            value.clone()
+        };
+        let origin = match setting.origin {
+            Origin::Env => "env",
+            Origin::File => "config file",
+            Origin::Default | Origin::Unset => "default",
+        };
+        out.push_str(&format!(
+            "{} = {}  # {}\n",
+            key,
+            toml::Value::String(value),
+            origin
+        ));
+    }
+    out
+}
+
+fn redact_url_password(value: &str) -> String {
+    match reqwest::Url::parse(value) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some("***"));
+            url.to_string()
+        }
+        Ok(_) => value.to_string(),
+        // Unparseable URLs may still contain credentials
+        Err(_) => "<redacted>".to_string(),
+    }
 }
 
 impl Config {
-    pub fn from_env() -> Result<Self, ConfigError> {
-        let database_url =
-            env::var("DATABASE_URL").map_err(|_| ConfigError::MissingEnv("DATABASE_URL".into()))?;
-        let redis_url =
-            env::var("REDIS_URL").map_err(|_| ConfigError::MissingEnv("REDIS_URL".into()))?;
-        let jwt_secret =
-            env::var("JWT_SECRET").map_err(|_| ConfigError::MissingEnv("JWT_SECRET".into()))?;
-        let port = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
+    /// Loads the configuration from the environment, layered over the given
+    /// TOML file, and validates it.
+    pub fn load(file: Option<&Path>) -> Result<Self, ConfigError> {
+        Self::load_with_settings(file).map(|(config, _)| config)
+    }
+
+    /// Like [`Config::load`], also returning every setting with its origin.
+    pub fn load_with_settings(file: Option<&Path>) -> Result<(Self, Vec<Setting>), ConfigError> {
+        let src = Sources::load(file)?;
+        let config = Self::from_sources(&src)?;
+        src.check_unused()?;
+        Ok((config, src.resolved.into_inner()))
+    }
+
+    fn from_sources(src: &Sources) -> Result<Self, ConfigError> {
+        let database_url = src.required("DATABASE_URL")?;
+        let redis_url = src.required("REDIS_URL")?;
+        let jwt_secret = src.get("JWT_SECRET")?;
+        // Deployments that only set a secret keep HS256, so their tokens stay valid
+        let default_algorithm = if jwt_secret.is_some() {
+            "HS256"
+        } else {
+            "EdDSA"
+        };
+        let jwt_algorithm = match src.string_or("JWT_ALGORITHM", default_algorithm)?.as_str() {
+            "HS256" => Algorithm::HS256,
+            "RS256" => Algorithm::RS256,
+            "EdDSA" => Algorithm::EdDSA,
+            other => {
+                return Err(ConfigError::InvalidValue(
+                    "JWT_ALGORITHM".into(),
+                    other.into(),
+                ))
+            }
+        };
+        if jwt_algorithm == Algorithm::HS256 && jwt_secret.is_none() {
+            return Err(ConfigError::MissingEnv("JWT_SECRET".into()));
+        }
+        let jwt_key_rotation_hours = src.parse("JWT_KEY_ROTATION_HOURS", 720u64)?;
+        let jwt_key_refresh_seconds = src.parse("JWT_KEY_REFRESH_SECONDS", 300u64)?;
+        if jwt_key_rotation_hours == 0 || jwt_key_refresh_seconds == 0 {
+            return Err(ConfigError::InvalidValue(
+                "JWT_KEY_ROTATION_HOURS / JWT_KEY_REFRESH_SECONDS".into(),
+                "0".into(),
+            ));
+        }
+        let token_ttl_minutes = src.parse("TOKEN_TTL_MINUTES", 60i64)?;
+        if token_ttl_minutes <= 0 {
+            return Err(ConfigError::InvalidValue(
+                "TOKEN_TTL_MINUTES".into(),
+                token_ttl_minutes.to_string(),
+            ));
+        }
+        let host = src.parse("HOST", IpAddr::from([0, 0, 0, 0]))?;
+        // Single addresses are accepted as /32 or /128 networks
+        let trusted_proxies = parse_list(
+            "TRUSTED_PROXIES",
+            &src.string_or("TRUSTED_PROXIES", "")?,
+            |v| {
+                v.parse::<IpNet>()
+                    .ok()
+                    .or_else(|| v.parse::<IpAddr>().ok().map(IpNet::from))
+            },
+        )?;
+        let port = src.string_or("PORT", "8080")?;
         let port = port
             .parse::<u16>()
             .map_err(|_| ConfigError::InvalidPort(port))?;
+        let db_max_connections = src.parse("DB_MAX_CONNECTIONS", 10u32)?;
+        let db_min_connections = src.parse("DB_MIN_CONNECTIONS", 0u32)?;
+        if db_max_connections == 0 || db_min_connections > db_max_connections {
+            return Err(ConfigError::InvalidValue(
+                "DB_MIN_CONNECTIONS / DB_MAX_CONNECTIONS".into(),
+                format!("{} / {}", db_min_connections, db_max_connections),
+            ));
+        }
+        let db_acquire_timeout_seconds = src.parse("DB_ACQUIRE_TIMEOUT_SECONDS", 30u64)?;
+        let http_client_timeout_seconds = src.parse("HTTP_CLIENT_TIMEOUT_SECONDS", 10u64)?;
+        for (key, value) in [
+            ("DB_ACQUIRE_TIMEOUT_SECONDS", db_acquire_timeout_seconds),
+            ("HTTP_CLIENT_TIMEOUT_SECONDS", http_client_timeout_seconds),
+        ] {
+            if value == 0 {
+                return Err(ConfigError::InvalidValue(key.into(), "0".into()));
+            }
+        }
+        // WebAuthn relying party: the domain passkeys are bound to and the frontend origin
+        let webauthn_rp_id = src.string_or("WEBAUTHN_RP_ID", "localhost")?;
+        let webauthn_rp_origin =
+            src.string_or("WEBAUTHN_RP_ORIGIN", format!("http://localhost:{}", port))?;
+        let oidc_providers = src
+            .get("OIDC_PROVIDERS")?
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| OidcProviderConfig::from_sources(src, name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let session_cookie_secure = src.parse("SESSION_COOKIE_SECURE", true)?;
+        let same_site = src.string_or("SESSION_COOKIE_SAMESITE", "Lax")?;
+        let session_cookie_same_site = parse_same_site(&same_site).ok_or_else(|| {
+            ConfigError::InvalidValue("SESSION_COOKIE_SAMESITE".into(), same_site.clone())
+        })?;
+        // Browsers reject SameSite=None cookies without Secure
+        if session_cookie_same_site == SameSite::None && !session_cookie_secure {
+            return Err(ConfigError::InvalidValue(
+                "SESSION_COOKIE_SAMESITE".into(),
+                same_site,
+            ));
+        }
+        let argon2_memory_kib = src.parse("ARGON2_MEMORY_KIB", argon2::Params::DEFAULT_M_COST)?;
+        let argon2_iterations = src.parse("ARGON2_ITERATIONS", argon2::Params::DEFAULT_T_COST)?;
+        let argon2_parallelism = src.parse("ARGON2_PARALLELISM", argon2::Params::DEFAULT_P_COST)?;
+        if let Err(e) = argon2::Params::new(
+            argon2_memory_kib,
+            argon2_iterations,
+            argon2_parallelism,
+            None,
+        ) {
+            return Err(ConfigError::InvalidValue("ARGON2_*".into(), e.to_string()));
+        }
+        let password_pepper = src.get("PASSWORD_PEPPER")?;
+        let password_min_length = src.parse("PASSWORD_MIN_LENGTH", 8usize)?;
+        let password_min_score = src.parse("PASSWORD_MIN_SCORE", 2u8)?;
+        if password_min_score > 4 {
+            return Err(ConfigError::InvalidValue(
+                "PASSWORD_MIN_SCORE".into(),
+                password_min_score.to_string(),
+            ));
+        }
+        let breached_passwords_dir = src.get("BREACHED_PASSWORDS_DIR")?.map(PathBuf::from);
+        if let Some(dir) = &breached_passwords_dir {
+            if !dir.is_dir() {
+                return Err(ConfigError::InvalidValue(
+                    "BREACHED_PASSWORDS_DIR".into(),
+                    dir.display().to_string(),
+                ));
+            }
+        }
+        let default_time_zone = src.parse("DEFAULT_TIME_ZONE", Tz::Asia__Shanghai)?;
+        let health_check_timeout_ms = src.parse("HEALTH_CHECK_TIMEOUT_MS", 1000u64)?;
+        let redis_failure_policy = src.parse("REDIS_FAILURE_POLICY", RedisFailurePolicy::Closed)?;
+        let redis_reconnect_interval_seconds =
+            src.parse("REDIS_RECONNECT_INTERVAL_SECONDS", 5u64)?;
+        if redis_reconnect_interval_seconds == 0 {
+            return Err(ConfigError::InvalidValue(
+                "REDIS_RECONNECT_INTERVAL_SECONDS".into(),
+                "0".into(),
+            ));
+        }
+        let redis_connect_timeout_ms = src.parse("REDIS_CONNECT_TIMEOUT_MS", 1000u64)?;
+        let redis_response_timeout_ms = src.parse("REDIS_RESPONSE_TIMEOUT_MS", 500u64)?;
+        let metrics_token = src.get("METRICS_TOKEN")?;
+        let otlp_endpoint = src.get("OTEL_EXPORTER_OTLP_ENDPOINT")?;
+        let otel_service_name = src.string_or("OTEL_SERVICE_NAME", "tiny-note-backend")?;
+        let otel_traces_sample_ratio = src.parse("OTEL_TRACES_SAMPLE_RATIO", 1.0f64)?;
+        let log_format = src.parse("LOG_FORMAT", LogFormat::Text)?;
+        let shutdown_timeout_seconds = src.parse("SHUTDOWN_TIMEOUT_SECONDS", 25u64)?;
+        let tls = TlsConfig::from_sources(src, port)?;
+        if !(0.0..=1.0).contains(&otel_traces_sample_ratio) {
+            return Err(ConfigError::InvalidValue(
+                "OTEL_TRACES_SAMPLE_RATIO".into(),
+                otel_traces_sample_ratio.to_string(),
+            ));
+        }
+        // The frontend is the one origin every deployment has
+        let cors_allowed_origins = parse_list(
+            "CORS_ALLOWED_ORIGINS",
+            &src.string_or("CORS_ALLOWED_ORIGINS", webauthn_rp_origin.clone())?,
+            |v| v.parse::<OriginPattern>().ok(),
+        )?;
+        let cors_allowed_methods = parse_list(
+            "CORS_ALLOWED_METHODS",
+            &src.string_or("CORS_ALLOWED_METHODS", DEFAULT_CORS_METHODS)?,
+            |v| Method::from_bytes(v.to_ascii_uppercase().as_bytes()).ok(),
+        )?;
+        let cors_allowed_headers = parse_list(
+            "CORS_ALLOWED_HEADERS",
+            &src.string_or("CORS_ALLOWED_HEADERS", DEFAULT_CORS_HEADERS)?,
+            |v| HeaderName::from_bytes(v.to_ascii_lowercase().as_bytes()).ok(),
+        )?;
+        let cors_max_age_seconds = src.parse("CORS_MAX_AGE_SECONDS", 600u64)?;
+        let cors_permissive = src.parse("CORS_PERMISSIVE", false)?;
         Ok(Self {
             database_url,
             redis_url,
             jwt_secret,
+            jwt_algorithm,
+            jwt_key_rotation_hours,
+            jwt_key_refresh_seconds,
+            token_ttl_minutes,
+            host,
             port,
+            trusted_proxies,
+            db_max_connections,
+            db_min_connections,
+            db_acquire_timeout_seconds,
+            http_client_timeout_seconds,
+            webauthn_rp_id,
+            webauthn_rp_origin,
+            oidc_providers,
+            session_cookie_secure,
+            session_cookie_same_site,
+            argon2_memory_kib,
+            argon2_iterations,
+            argon2_parallelism,
+            password_pepper,
+            password_min_length,
+            password_min_score,
+            breached_passwords_dir,
+            default_time_zone,
+            health_check_timeout_ms,
+            redis_failure_policy,
+            redis_reconnect_interval_seconds,
+            redis_connect_timeout_ms,
+            redis_response_timeout_ms,
+            metrics_token,
+            otlp_endpoint,
+            otel_service_name,
+            otel_traces_sample_ratio,
+            log_format,
+            shutdown_timeout_seconds,
+            tls,
+            cors_allowed_origins,
+            cors_allowed_methods,
+            cors_allowed_headers,
+            cors_max_age_seconds,
+            cors_permissive,
+        })
+    }
+}
+
+impl TlsConfig {
+    fn from_sources(src: &Sources, https_port: u16) -> Result<Option<Self>, ConfigError> {
+        let cert_path = src.get("TLS_CERT_PATH")?.map(PathBuf::from);
+        let key_path = src.get("TLS_KEY_PATH")?.map(PathBuf::from);
+        let reload_interval_seconds = src.parse("TLS_RELOAD_INTERVAL_SECONDS", 30u64)?;
+        if reload_interval_seconds == 0 {
+            return Err(ConfigError::InvalidValue(
+                "TLS_RELOAD_INTERVAL_SECONDS".into(),
+                "0".into(),
+            ));
+        }
+        let redirect_port = match src.get("TLS_REDIRECT_PORT")? {
+            Some(port) => Some(
+                port.trim()
+                    .parse::<u16>()
+                    .map_err(|_| ConfigError::InvalidPort(port))?,
+            ),
+            None => None,
+        };
+        let (cert_path, key_path) = match (cert_path, key_path) {
+            (Some(cert_path), Some(key_path)) => (cert_path, key_path),
+            (Some(_), None) => return Err(ConfigError::MissingEnv("TLS_KEY_PATH".into())),
+            (None, Some(_)) => return Err(ConfigError::MissingEnv("TLS_CERT_PATH".into())),
+            (None, None) => {
+                return match redirect_port {
+                    Some(port) => Err(ConfigError::InvalidValue(
+                        "TLS_REDIRECT_PORT".into(),
+                        format!("{} (requires TLS_CERT_PATH and TLS_KEY_PATH)", port),
+                    )),
+                    None => Ok(None),
+                };
+            }
+        };
+        if redirect_port == Some(https_port) {
+            return Err(ConfigError::InvalidValue(
+                "TLS_REDIRECT_PORT".into(),
+                format!("{} (same as PORT)", https_port),
+            ));
+        }
+        Ok(Some(Self {
+            cert_path,
+            key_path,
+            reload_interval_seconds,
+            redirect_port,
+        }))
+    }
+}
+
+impl OidcProviderConfig {
+    fn from_sources(src: &Sources, name: &str) -> Result<Self, ConfigError> {
+        let prefix = format!("OIDC_{}_", name.to_uppercase().replace('-', "_"));
+        let key = |suffix: &str| format!("{}{}", prefix, suffix);
+        Ok(Self {
+            name: name.to_lowercase(),
+            issuer: src
+                .required(&key("ISSUER"))?
+                .trim_end_matches('/')
+                .to_string(),
+            client_id: src.required(&key("CLIENT_ID"))?,
+            client_secret: src.get(&key("CLIENT_SECRET"))?,
+            redirect_url: src.required(&key("REDIRECT_URL"))?,
+            scopes: src.string_or(&key("SCOPES"), "openid email profile")?,
         })
     }
 }
+
+#[cfg(test)]
+impl Config {
+    /// The required settings plus `toml`, e.g. `"password_min_score = 3"`.
+    pub fn for_tests(toml: &str) -> Config {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let required =
+            "database_url = \"mysql://test@localhost/test\"\nredis_url = \"redis://localhost\"";
+        std::fs::write(file.path(), format!("{}\n{}\n", required, toml)).unwrap();
+        Config::load(Some(file.path())).unwrap()
+    }
+}
@@ -1,2 +1,3 @@
 pub mod mysql;
-pub mod redis;
\ No newline at end of file
+pub mod redis;
+pub mod revocations;
@@ -1,5 +1,12 @@
+use std::ops::Deref;
+
 use crate::config::Config;
-use sqlx::{mysql::MySqlPoolOptions, MySqlPool};
+use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
+use sqlx::{
+    mysql::{MySqlPoolOptions, MySqlQueryResult, MySqlRow, MySqlStatement, MySqlTypeInfo},
+    Describe, Either, Execute, Executor, MySql, MySqlPool,
+};
+use tracing::{field::Empty, Instrument, Span};
 
 #[derive(Debug)]
 pub enum DbError {
@@ -17,13 +24,198 @@ impl std::fmt::Display for DbError {
 impl std::error::Error for DbError {}
 
 impl From<sqlx::Error> for DbError {
-    fn from(e: sqlx::Error) -> Self { DbError::Pool(e) }
+    fn from(e: sqlx::Error) -> Self {
+        DbError::Pool(e)
+    }
+}
+
+/// The shared MySQL pool. Queries executed on it get a `db.query` span each;
+/// for a transaction, run them on [`Traced`]`(&mut *tx)`.
+#[derive(Debug, Clone)]
+pub struct TracedPool(MySqlPool);
+
+impl Deref for TracedPool {
+    type Target = MySqlPool;
+
+    fn deref(&self) -> &MySqlPool {
+        &self.0
+    }
+}
+
+impl From<MySqlPool> for TracedPool {
+    fn from(pool: MySqlPool) -> Self {
+        TracedPool(pool)
+    }
+}
+
+impl<'c> Executor<'c> for &'c TracedPool {
+    type Database = MySql;
+
+    fn fetch_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxStream<'e, Result<Either<MySqlQueryResult, MySqlRow>, sqlx::Error>>
+    where
+        'c: 'e,
+        E: 'q + Execute<'q, MySql>,
+    {
+        Traced(&self.0).fetch_many(query)
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxFuture<'e, Result<Option<MySqlRow>, sqlx::Error>>
+    where
+        'c: 'e,
+        E: 'q + Execute<'q, MySql>,
+    {
+        Traced(&self.0).fetch_optional(query)
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [MySqlTypeInfo],
+    ) -> BoxFuture<'e, Result<MySqlStatement<'q>, sqlx::Error>>
+    where
+        'c: 'e,
+    {
+        self.0.prepare_with(sql, parameters)
+    }
+
+    fn describe<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+    ) -> BoxFuture<'e, Result<Describe<MySql>, sqlx::Error>>
+    where
+        'c: 'e,
+    {
+        self.0.describe(sql)
+    }
 }
 
-pub async fn create_mysql_pool(cfg: &Config) -> Result<MySqlPool, DbError> {
+/// Wraps any MySQL executor so that each query runs in a `db.query` span
+/// carrying the SQL text (with `?` placeholders, never the bound values).
+#[derive(Debug)]
+pub struct Traced<E>(pub E);
+
+fn query_span(sql: &str) -> Span {
+    let operation = sql
+        .split_whitespace()
+        .next()
+        .unwrap_or("QUERY")
+        .to_ascii_uppercase();
+    tracing::info_span!(
+        "db.query",
+        otel.name = %operation,
+        otel.kind = "client",
+        otel.status_code = Empty,
+        db.system = "mysql",
+        db.operation.name = %operation,
+        db.query.text = sql,
+    )
+}
+
+fn record_error(span: &Span) {
+    span.record("otel.status_code", "ERROR");
+}
+
+impl<'c, X> Executor<'c> for Traced<X>
+where
+    X: Executor<'c, Database = MySql>,
+{
+    type Database = MySql;
+
+    fn fetch_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxStream<'e, Result<Either<MySqlQueryResult, MySqlRow>, sqlx::Error>>
+    where
+        'c: 'e,
+        E: 'q + Execute<'q, MySql>,
+    {
+        let span = query_span(query.sql());
+        let mut rows = {
+            let _entered = span.enter();
+            self.0.fetch_many(query)
+        };
+        // The span lasts until the stream is dropped, i.e. until all rows are read
+        futures::stream::poll_fn(move |cx| {
+            let _entered = span.enter();
+            let item = rows.poll_next_unpin(cx);
+            if let std::task::Poll::Ready(Some(Err(_))) = &item {
+                record_error(&span);
+            }
+            item
+        })
+        .boxed()
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxFuture<'e, Result<Option<MySqlRow>, sqlx::Error>>
+    where
+        'c: 'e,
+        E: 'q + Execute<'q, MySql>,
+    {
+        let span = query_span(query.sql());
+        let fut = self.0.fetch_optional(query);
+        async move {
+            let res = fut.await;
+            if res.is_err() {
+                record_error(&Span::current());
+            }
+            res
+        }
+        .instrument(span)
+        .boxed()
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [MySqlTypeInfo],
+    ) -> BoxFuture<'e, Result<MySqlStatement<'q>, sqlx::Error>>
+    where
+        'c: 'e,
+    {
+        self.0.prepare_with(sql, parameters)
+    }
+
+    fn describe<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+    ) -> BoxFuture<'e, Result<Describe<MySql>, sqlx::Error>>
+    where
+        'c: 'e,
+    {
+        self.0.describe(sql)
+    }
+}
+
+pub async fn create_mysql_pool(cfg: &Config) -> Result<TracedPool, DbError> {
     let pool = MySqlPoolOptions::new()
-        .max_connections(10)
+        .max_connections(cfg.db_max_connections)
+        .min_connections(cfg.db_min_connections)
+        .acquire_timeout(std::time::Duration::from_secs(
+            cfg.db_acquire_timeout_seconds,
+        ))
+        // Timestamps are stored in UTC, including column defaults such as CURRENT_TIMESTAMP
+        .after_connect(|conn, _meta| {
+            Box::pin(async move {
+                conn.execute("SET time_zone = '+00:00'").await?;
+                Ok(())
+            })
+        })
         .connect(&cfg.database_url)
         .await?;
-    Ok(pool)
-}
\ No newline at end of file
+    Ok(pool.into())
+}
+
+/// Round trip used by the readiness probe.
+pub async fn ping(pool: &TracedPool) -> Result<(), DbError> {
+    sqlx::query("SELECT 1").execute(pool).await?;
+    Ok(())
+}
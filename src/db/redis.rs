@@ -1,15 +1,34 @@
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use crate::config::Config;
-use redis::{AsyncCommands, Client};
+use redis::{
+    aio::{ConnectionLike, ConnectionManager},
+    AsyncCommands, Client, Cmd, Pipeline, RedisFuture, RedisResult, Value,
+};
+use tokio::sync::OnceCell;
+
+/// Reconnect attempts after a dropped connection, spaced by a jittered
+/// exponential backoff of up to 100ms, 200ms, ...
+const RECONNECT_RETRIES: usize = 2;
+const RECONNECT_BACKOFF_BASE: u64 = 2;
+const RECONNECT_BACKOFF_FACTOR_MS: u64 = 100;
 
 #[derive(Debug)]
 pub enum RedisError {
     Client(redis::RedisError),
+    /// Marked down; skipped until the reconnect task reaches it again
+    Unavailable,
 }
 
 impl std::fmt::Display for RedisError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             RedisError::Client(e) => write!(f, "redis client error: {}", e),
+            RedisError::Unavailable => write!(f, "redis unavailable"),
         }
     }
 }
@@ -17,24 +36,298 @@ impl std::fmt::Display for RedisError {
 impl std::error::Error for RedisError {}
 
 impl From<redis::RedisError> for RedisError {
-    fn from(e: redis::RedisError) -> Self { RedisError::Client(e) }
+    fn from(e: redis::RedisError) -> Self {
+        RedisError::Client(e)
+    }
+}
+
+/// The long-lived Redis connection shared through `AppState`.
+///
+/// All commands are multiplexed over one [`ConnectionManager`], which
+/// reconnects in the background when the connection drops. The manager is
+/// created on first use, so the service starts even while Redis is down.
+#[derive(Clone)]
+pub struct RedisConnection {
+    client: Client,
+    response_timeout: Duration,
+    connect_timeout: Duration,
+    manager: Arc<OnceCell<ConnectionManager>>,
+}
+
+impl RedisConnection {
+    async fn get(&self) -> Result<TimedConnection, RedisError> {
+        let manager = self
+            .manager
+            .get_or_try_init(|| {
+                ConnectionManager::new_with_backoff_and_timeouts(
+                    self.client.clone(),
+                    RECONNECT_BACKOFF_BASE,
+                    RECONNECT_BACKOFF_FACTOR_MS,
+                    RECONNECT_RETRIES,
+                    self.response_timeout,
+                    self.connect_timeout,
+                )
+            })
+            .await
+            .inspect_err(|_| metrics::counter!("redis_connection_errors_total").increment(1))?;
+        Ok(TimedConnection(manager.clone()))
+    }
 }
 
-pub fn create_redis_client(cfg: &Config) -> Result<Client, RedisError> {
+/// Only parses `REDIS_URL`; no connection is opened until the first command.
+pub fn create_redis_client(cfg: &Config) -> Result<RedisConnection, RedisError> {
     let client = Client::open(cfg.redis_url.clone())?;
-    Ok(client)
+    Ok(RedisConnection {
+        client,
+        response_timeout: Duration::from_millis(cfg.redis_response_timeout_ms),
+        connect_timeout: Duration::from_millis(cfg.redis_connect_timeout_ms),
+        manager: Arc::new(OnceCell::new()),
+    })
 }
 
-pub async fn is_token_blacklisted(client: &Client, jti: &str) -> Result<bool, RedisError> {
-    let mut conn = client.get_multiplexed_async_connection().await?;
+/// A handle on the shared connection that records the latency of every
+/// command in the `redis_command_duration_seconds` histogram.
+struct TimedConnection(ConnectionManager);
+
+impl ConnectionLike for TimedConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(timed(command_name(cmd), self.0.req_packed_command(cmd)))
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(timed(
+            "pipeline".to_string(),
+            self.0.req_packed_commands(cmd, offset, count),
+        ))
+    }
+
+    fn get_db(&self) -> i64 {
+        self.0.get_db()
+    }
+}
+
+fn command_name(cmd: &Cmd) -> String {
+    match cmd.args_iter().next() {
+        Some(redis::Arg::Simple(name)) => String::from_utf8_lossy(name).to_ascii_lowercase(),
+        _ => "unknown".to_string(),
+    }
+}
+
+async fn timed<T>(command: String, fut: impl Future<Output = RedisResult<T>>) -> RedisResult<T> {
+    let started = Instant::now();
+    let result = fut.await;
+    let status = if result.is_ok() { "ok" } else { "error" };
+    metrics::histogram!("redis_command_duration_seconds", "command" => command, "status" => status)
+        .record(started.elapsed().as_secs_f64());
+    result
+}
+
+/// Round trip used by the readiness probe.
+pub async fn ping(redis: &RedisConnection) -> Result<(), RedisError> {
+    let mut conn = redis.get().await?;
+    let _: String = redis::cmd("PING").query_async(&mut conn).await?;
+    Ok(())
+}
+
+pub async fn is_token_blacklisted(redis: &RedisConnection, jti: &str) -> Result<bool, RedisError> {
+    let mut conn = redis.get().await?;
     let exists: i64 = conn.exists(format!("bl:{}", jti)).await?;
     Ok(exists > 0)
 }
 
-#[allow(dead_code)]
-pub async fn blacklist_token(client: &Client, jti: &str, ttl_seconds: i64) -> Result<(), RedisError> {
-    let mut conn = client.get_multiplexed_async_connection().await?;
+pub async fn blacklist_token(
+    redis: &RedisConnection,
+    jti: &str,
+    ttl_seconds: i64,
+) -> Result<(), RedisError> {
+    let mut conn = redis.get().await?;
     let key = format!("bl:{}", jti);
     let _: () = conn.set_ex(key, 1, ttl_seconds as u64).await?;
     Ok(())
-}
\ No newline at end of file
+}
+pub async fn store_login_challenge(
+    redis: &RedisConnection,
+    challenge: &str,
+    user_id: uuid::Uuid,
+    ttl_seconds: u64,
+) -> Result<(), RedisError> {
+    let mut conn = redis.get().await?;
+    let key = format!("mfa:{}", challenge);
+    let _: () = conn.set_ex(key, user_id.to_string(), ttl_seconds).await?;
+    Ok(())
+}
+
+pub async fn get_login_challenge(
+    redis: &RedisConnection,
+    challenge: &str,
+) -> Result<Option<uuid::Uuid>, RedisError> {
+    let mut conn = redis.get().await?;
+    let value: Option<String> = conn.get(format!("mfa:{}", challenge)).await?;
+    Ok(value.and_then(|v| uuid::Uuid::parse_str(&v).ok()))
+}
+
+/// Counts a failed code attempt against a challenge and returns the running total.
+pub async fn record_challenge_attempt(
+    redis: &RedisConnection,
+    challenge: &str,
+    ttl_seconds: i64,
+) -> Result<i64, RedisError> {
+    let mut conn = redis.get().await?;
+    let key = format!("mfa_attempts:{}", challenge);
+    let attempts: i64 = conn.incr(&key, 1).await?;
+    if attempts == 1 {
+        let _: () = conn.expire(&key, ttl_seconds).await?;
+    }
+    Ok(attempts)
+}
+
+pub async fn delete_login_challenge(
+    redis: &RedisConnection,
+    challenge: &str,
+) -> Result<(), RedisError> {
+    let mut conn = redis.get().await?;
+    let _: () = conn
+        .del(&[
+            format!("mfa:{}", challenge),
+            format!("mfa_attempts:{}", challenge),
+        ])
+        .await?;
+    Ok(())
+}
+
+/// Redeems a challenge with `GETDEL`, so of concurrent callers only one
+/// gets the user ID back.
+pub async fn take_login_challenge(
+    redis: &RedisConnection,
+    challenge: &str,
+) -> Result<Option<uuid::Uuid>, RedisError> {
+    let mut conn = redis.get().await?;
+    let value: Option<String> = conn.get_del(format!("mfa:{}", challenge)).await?;
+    let _: () = conn.del(format!("mfa_attempts:{}", challenge)).await?;
+    Ok(value.and_then(|v| uuid::Uuid::parse_str(&v).ok()))
+}
+
+/// Stores a short-lived JSON value, e.g. WebAuthn ceremony state.
+pub async fn store_json<T: serde::Serialize>(
+    redis: &RedisConnection,
+    key: &str,
+    value: &T,
+    ttl_seconds: u64,
+) -> Result<(), RedisError> {
+    let payload = serde_json::to_string(value).map_err(|e| {
+        redis::RedisError::from((
+            redis::ErrorKind::TypeError,
+            "serialize error",
+            e.to_string(),
+        ))
+    })?;
+    let mut conn = redis.get().await?;
+    let _: () = conn.set_ex(key, payload, ttl_seconds).await?;
+    Ok(())
+}
+
+/// Reads and deletes a JSON value stored with [`store_json`], so it can be used only once.
+pub async fn take_json<T: serde::de::DeserializeOwned>(
+    redis: &RedisConnection,
+    key: &str,
+) -> Result<Option<T>, RedisError> {
+    let mut conn = redis.get().await?;
+    let payload: Option<String> = conn.get_del(key).await?;
+    match payload {
+        Some(p) => serde_json::from_str(&p).map(Some).map_err(|e| {
+            RedisError::Client(redis::RedisError::from((
+                redis::ErrorKind::TypeError,
+                "deserialize error",
+                e.to_string(),
+            )))
+        }),
+        None => Ok(None),
+    }
+}
+
+/// Records a login session under `session:<jti>` and indexes it per user.
+pub async fn store_session(
+    redis: &RedisConnection,
+    user_id: uuid::Uuid,
+    jti: &str,
+    fields: &[(&str, String)],
+    ttl_seconds: i64,
+) -> Result<(), RedisError> {
+    let mut conn = redis.get().await?;
+    let key = format!("session:{}", jti);
+    let index = format!("user_sessions:{}", user_id);
+    let _: () = redis::pipe()
+        .hset_multiple(&key, fields)
+        .expire(&key, ttl_seconds)
+        .sadd(&index, jti)
+        // The index outlives every session it lists
+        .expire(&index, ttl_seconds)
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+/// Updates `last_seen_at` of a session that still exists.
+pub async fn touch_session(redis: &RedisConnection, jti: &str, now: i64) -> Result<(), RedisError> {
+    // One script, so a session that expires or is revoked meanwhile is not
+    // recreated without a TTL
+    let script = redis::Script::new(
+        "if redis.call('EXISTS', KEYS[1]) == 1 then redis.call('HSET', KEYS[1], 'last_seen_at', ARGV[1]) end",
+    );
+    let mut conn = redis.get().await?;
+    let _: () = script
+        .key(format!("session:{}", jti))
+        .arg(now)
+        .invoke_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+/// Returns all live sessions of a user, pruning index entries whose session expired.
+pub async fn list_sessions(
+    redis: &RedisConnection,
+    user_id: uuid::Uuid,
+) -> Result<Vec<(String, std::collections::HashMap<String, String>)>, RedisError> {
+    let mut conn = redis.get().await?;
+    let index = format!("user_sessions:{}", user_id);
+    let jtis: Vec<String> = conn.smembers(&index).await?;
+    let mut sessions = Vec::new();
+    for jti in jtis {
+        let fields: std::collections::HashMap<String, String> =
+            conn.hgetall(format!("session:{}", jti)).await?;
+        if fields.is_empty() {
+            let _: () = conn.srem(&index, &jti).await?;
+        } else {
+            sessions.push((jti, fields));
+        }
+    }
+    Ok(sessions)
+}
+
+pub async fn get_session(
+    redis: &RedisConnection,
+    jti: &str,
+) -> Result<std::collections::HashMap<String, String>, RedisError> {
+    let mut conn = redis.get().await?;
+    let fields = conn.hgetall(format!("session:{}", jti)).await?;
+    Ok(fields)
+}
+
+pub async fn delete_session(
+    redis: &RedisConnection,
+    user_id: uuid::Uuid,
+    jti: &str,
+) -> Result<(), RedisError> {
+    let mut conn = redis.get().await?;
+    let _: () = redis::pipe()
+        .del(format!("session:{}", jti))
+        .srem(format!("user_sessions:{}", user_id), jti)
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use super::redis::{self as store, RedisConnection, RedisError};
+use crate::config::RedisFailurePolicy;
+use crate::utils::shutdown::Shutdown;
+
+/// Token revocations (`bl:<jti>` in Redis) mirrored in process memory, so a
+/// token revoked through this instance stays rejected while Redis is down.
+///
+/// Revocations that could not be written to Redis are kept as pending and
+/// replayed by [`spawn_reconnect`] once Redis answers again. While Redis is
+/// marked down, lookups skip it and [`RedisFailurePolicy`] decides whether
+/// tokens not known to be revoked are accepted.
+#[derive(Clone)]
+pub struct Revocations {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    policy: RedisFailurePolicy,
+    redis_up: AtomicBool,
+    /// `jti` -> token expiry (Unix seconds) of revocations seen by this process
+    local: Mutex<HashMap<String, i64>>,
+    /// Revocations not yet stored in Redis
+    pending: Mutex<HashMap<String, i64>>,
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+impl Revocations {
+    pub fn new(policy: RedisFailurePolicy) -> Self {
+        Revocations {
+            inner: Arc::new(Inner {
+                policy,
+                redis_up: AtomicBool::new(true),
+                local: Mutex::default(),
+                pending: Mutex::default(),
+            }),
+        }
+    }
+
+    pub fn policy(&self) -> RedisFailurePolicy {
+        self.inner.policy
+    }
+
+    pub fn is_redis_up(&self) -> bool {
+        self.inner.redis_up.load(Ordering::Relaxed)
+    }
+
+    /// Records a Redis round trip; logs when the state changes.
+    pub fn mark_redis(&self, up: bool) {
+        let was_up = self.inner.redis_up.swap(up, Ordering::Relaxed);
+        if was_up && !up {
+            tracing::warn!(policy = ?self.inner.policy, "Redis unavailable, using the local revocation cache");
+        }
+    }
+
+    /// Revokes a token until `expires_at`. Never fails: if Redis cannot be
+    /// written the revocation is kept locally and replayed later.
+    pub async fn revoke(&self, redis: &RedisConnection, jti: &str, expires_at: i64) {
+        let ttl = expires_at - now();
+        if ttl <= 0 {
+            return;
+        }
+        self.inner
+            .local
+            .lock()
+            .unwrap()
+            .insert(jti.to_string(), expires_at);
+        if self.is_redis_up() {
+            match store::blacklist_token(redis, jti, ttl).await {
+                Ok(()) => return,
+                Err(e) => {
+                    tracing::warn!(error = %e, jti = %jti, "failed to store revocation in Redis");
+                    self.mark_redis(false);
+                }
+            }
+        }
+        self.inner
+            .pending
+            .lock()
+            .unwrap()
+            .insert(jti.to_string(), expires_at);
+    }
+
+    /// Whether the token was revoked. Errors only with the fail-closed policy
+    /// when Redis cannot be asked.
+    pub async fn is_revoked(
+        &self,
+        redis: &RedisConnection,
+        jti: &str,
+        expires_at: i64,
+    ) -> Result<bool, RedisError> {
+        if self.inner.local.lock().unwrap().contains_key(jti) {
+            return Ok(true);
+        }
+        let lookup = if self.is_redis_up() {
+            store::is_token_blacklisted(redis, jti).await
+        } else {
+            Err(RedisError::Unavailable)
+        };
+        match lookup {
+            Ok(revoked) => {
+                if revoked {
+                    self.inner
+                        .local
+                        .lock()
+                        .unwrap()
+                        .insert(jti.to_string(), expires_at);
+                }
+                Ok(revoked)
+            }
+            Err(e) => {
+                if !matches!(e, RedisError::Unavailable) {
+                    self.mark_redis(false);
+                }
+                match self.inner.policy {
+                    RedisFailurePolicy::Open => Ok(false),
+                    RedisFailurePolicy::Closed => Err(e),
+                }
+            }
+        }
+    }
+
+    /// Drops expired entries, checks whether Redis is reachable and replays
+    /// pending revocations once it is.
+    async fn sync(&self, redis: &RedisConnection) {
+        let now = now();
+        self.inner.local.lock().unwrap().retain(|_, exp| *exp > now);
+        self.inner
+            .pending
+            .lock()
+            .unwrap()
+            .retain(|_, exp| *exp > now);
+        let pending_count = self.inner.pending.lock().unwrap().len();
+        if self.is_redis_up() && pending_count == 0 {
+            return;
+        }
+        if let Err(e) = store::ping(redis).await {
+            tracing::debug!(error = %e, "Redis still unavailable");
+            return;
+        }
+        let pending: Vec<(String, i64)> = self.inner.pending.lock().unwrap().drain().collect();
+        let mut replayed = 0;
+        for (jti, expires_at) in pending {
+            match store::blacklist_token(redis, &jti, expires_at - now).await {
+                Ok(()) => replayed += 1,
+                Err(_) => {
+                    self.inner.pending.lock().unwrap().insert(jti, expires_at);
+                }
+            }
+        }
+        if replayed > 0 || !self.is_redis_up() {
+            tracing::info!(replayed, "Redis reachable again");
+        }
+        self.inner.redis_up.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Background task that keeps [`Revocations`] in sync with Redis. On
+/// shutdown it syncs once more so pending revocations are not lost.
+pub fn spawn_reconnect(
+    shutdown: &Shutdown,
+    redis: RedisConnection,
+    revocations: Revocations,
+    period: Duration,
+) {
+    let stop = shutdown.token();
+    shutdown.spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            tokio::select! {
+                _ = stop.cancelled() => break,
+                _ = interval.tick() => revocations.sync(&redis).await,
+            }
+        }
+        revocations.sync(&redis).await;
+        let pending = revocations.inner.pending.lock().unwrap().len();
+        if pending > 0 {
+            tracing::warn!(pending, "revocations not written to Redis before shutdown");
+        }
+    });
+}
@@ -1,6 +1,12 @@
 // axum items are referenced with fully-qualified paths; remove unused imports
+use std::future::IntoFuture;
 use std::net::SocketAddr;
-use tracing::{info, Level};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use tracing::{info, warn, Level};
 
 mod config;
 mod db;
@@ -17,60 +23,248 @@ use db::{mysql::create_mysql_pool, redis::create_redis_client};
 
 #[derive(Clone)]
 pub struct AppState {
-    pub db: sqlx::MySqlPool,
-    pub redis: redis::Client,
-    pub jwt_secret: String,
+    pub db: db::mysql::TracedPool,
+    pub redis: db::redis::RedisConnection,
+    pub revocations: db::revocations::Revocations,
+    pub jwt_keys: utils::jwt::JwtKeys,
+    pub webauthn: Arc<webauthn_rs::Webauthn>,
+    pub http: reqwest::Client,
+    pub oidc: services::oidc_service::ProviderCache,
+    pub passwords: utils::password::PasswordHashing,
+    pub config: Arc<Config>,
+    pub metrics: metrics_exporter_prometheus::PrometheusHandle,
+}
+
+/// Used when neither `--config` nor `CONFIG_FILE` is given, if it exists
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+const USAGE: &str = "usage: main [--config <file>] [--print-config]";
+
+/// Command line options
+struct Args {
+    config: Option<PathBuf>,
+    /// Print the effective configuration (secrets redacted) and exit
+    print_config: bool,
 }
 
 fn main() {
     let rt = tokio::runtime::Runtime::new().unwrap();
-    rt.block_on(async_main()).unwrap();
+    if let Err(e) = rt.block_on(async_main()) {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn parse_args() -> anyhow::Result<Args> {
+    let mut args = Args {
+        config: None,
+        print_config: false,
+    };
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--config" => args.config = Some(iter.next().context(USAGE)?.into()),
+            "--print-config" => args.print_config = true,
+            other => anyhow::bail!("unknown argument {}; {}", other, USAGE),
+        }
+    }
+    Ok(args)
+}
+
+/// `--config`, else `CONFIG_FILE`, else `config.toml` in the working directory if present.
+fn config_file(args: &Args) -> Option<PathBuf> {
+    args.config
+        .clone()
+        .or_else(|| std::env::var_os("CONFIG_FILE").map(PathBuf::from))
+        .or_else(|| {
+            Path::new(DEFAULT_CONFIG_FILE)
+                .is_file()
+                .then(|| PathBuf::from(DEFAULT_CONFIG_FILE))
+        })
 }
 
 async fn async_main() -> anyhow::Result<()> {
     // Load env and init tracing
     dotenvy::dotenv().ok();
-    init_tracing();
-
-    let cfg = Config::from_env()?;
-
-    info!("Starting Tiny Note Backend in demo mode...");
-    info!("Note: Database connections are not required for this demo");
-    
-    // For demo purposes, we'll create minimal connections that won't be used
-    // In a real deployment, you would ensure MySQL and Redis are running
-    let pool = create_mysql_pool(&cfg).await.unwrap_or_else(|e| {
-        info!("MySQL not available: {}. API structure will still be demonstrated.", e);
-        // This will fail but we'll catch it in the routes
-        panic!("Demo mode: MySQL connection required for this demo")
-    });
+    let args = parse_args()?;
+    let file = config_file(&args);
+    let (cfg, settings) =
+        Config::load_with_settings(file.as_deref()).context("invalid configuration")?;
+    if args.print_config {
+        print!("{}", config::render_settings(&settings));
+        return Ok(());
+    }
+    let telemetry = init_tracing(&cfg)?;
+    let metrics = utils::metrics::install_recorder().context("installing the metrics recorder")?;
 
-    let redis = create_redis_client(&cfg).unwrap_or_else(|e| {
-        info!("Redis not available: {}. API structure will still be demonstrated.", e);
-        panic!("Demo mode: Redis connection required for this demo")
-    });
+    info!("Starting Tiny Note Backend...");
+    if let Some(path) = &file {
+        info!(config_file = %path.display(), "loaded config file");
+    }
+    if cfg.jwt_secret.is_some() && cfg.jwt_algorithm != jsonwebtoken::Algorithm::HS256 {
+        warn!(
+            "JWT_SECRET is ignored with JWT_ALGORITHM={}; tokens signed with it are no longer accepted",
+            utils::jwt::algorithm_name(cfg.jwt_algorithm)
+        );
+    }
+    if cfg.cors_permissive {
+        warn!("CORS_PERMISSIVE is on: any website can make credentialed requests; use only in development");
+    }
+
+    let shutdown = utils::shutdown::Shutdown::new();
+    shutdown.cancel_on_signal();
+
+    let pool = create_mysql_pool(&cfg)
+        .await
+        .context("connecting to MySQL")?;
+
+    // Connects lazily; an unreachable Redis degrades instead of stopping startup
+    let redis = create_redis_client(&cfg).context("invalid REDIS_URL")?;
+    let revocations = db::revocations::Revocations::new(cfg.redis_failure_policy);
+    if let Err(e) = db::redis::ping(&redis).await {
+        warn!(error = %e, "Redis unavailable at startup, will keep retrying");
+        revocations.mark_redis(false);
+    }
+    db::revocations::spawn_reconnect(
+        &shutdown,
+        redis.clone(),
+        revocations.clone(),
+        Duration::from_secs(cfg.redis_reconnect_interval_seconds),
+    );
+
+    let webauthn = services::passkey_service::build_webauthn(&cfg)?;
 
     let state = AppState {
         db: pool,
         redis,
-        jwt_secret: cfg.jwt_secret.clone(),
+        revocations,
+        jwt_keys: services::jwt_key_service::build_jwt_keys(&cfg),
+        webauthn: Arc::new(webauthn),
+        http: reqwest::Client::builder()
+            .timeout(Duration::from_secs(cfg.http_client_timeout_seconds))
+            .build()?,
+        oidc: Default::default(),
+        passwords: utils::password::PasswordHashing::from_config(&cfg)?,
+        config: Arc::new(cfg.clone()),
+        metrics,
     };
 
+    services::jwt_key_service::refresh_keys(&state).await?;
+    services::jwt_key_service::spawn_key_rotation(&shutdown, state.clone());
+
     let api = routes::build_router(&state);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], cfg.port));
-    info!("listening on {}", addr);
-    axum::serve(tokio::net::TcpListener::bind(addr).await?, api)
-        .await?;
+    let addr = SocketAddr::from((cfg.host, cfg.port));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let app = api.into_make_service_with_connect_info::<SocketAddr>();
+    // Once shutdown is requested the listener closes, idle keep-alive
+    // connections are closed and the server resolves when in-flight requests end
+    let mut server = match &cfg.tls {
+        Some(tls_cfg) => {
+            let tls = utils::tls::load(tls_cfg).await?;
+            utils::tls::spawn_reload(&shutdown, tls.clone(), tls_cfg.clone());
+            if let Some(port) = tls_cfg.redirect_port {
+                serve_https_redirect(&shutdown, SocketAddr::from((cfg.host, port)), cfg.port)
+                    .await?;
+            }
+            let handle = axum_server::Handle::new();
+            let drain = handle.clone();
+            let requested = shutdown.token();
+            tokio::spawn(async move {
+                requested.cancelled().await;
+                drain.graceful_shutdown(None);
+            });
+            info!("listening on https://{} (HTTP/2 and HTTP/1.1)", addr);
+            tokio::spawn(
+                axum_server::from_tcp_rustls(listener.into_std()?, tls)
+                    .handle(handle)
+                    .serve(app),
+            )
+        }
+        None => {
+            info!("listening on {}", addr);
+            tokio::spawn(
+                axum::serve(listener, app)
+                    .with_graceful_shutdown(shutdown.token().cancelled_owned())
+                    .into_future(),
+            )
+        }
+    };
+    tokio::select! {
+        res = &mut server => {
+            res.context("HTTP server panicked")?.context("HTTP server failed")?;
+            anyhow::bail!("HTTP server stopped unexpectedly");
+        }
+        _ = shutdown.requested() => {}
+    }
+
+    // Requests and background jobs share one deadline
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(cfg.shutdown_timeout_seconds);
+    let drained = tokio::time::timeout_at(deadline, async {
+        if let Ok(Err(e)) = server.await {
+            warn!(error = %e, "HTTP server failed while draining");
+        }
+        info!("connections drained, waiting for background tasks");
+    })
+    .await
+    .is_ok();
+    let finished = drained && shutdown.wait_for_tasks(deadline).await;
+    if finished {
+        // Every request has returned its connection, so this does not block
+        state.db.close().await;
+        info!("MySQL pool closed");
+    } else {
+        warn!(
+            timeout_seconds = cfg.shutdown_timeout_seconds,
+            "shutdown timed out, abandoning remaining requests and tasks"
+        );
+    }
+    // Dropping the last handle closes the shared Redis connection
+    drop(state);
+    telemetry.shutdown();
+    info!("shutdown complete");
     Ok(())
 }
 
-fn init_tracing() {
-    use tracing_subscriber::{fmt, EnvFilter};
+/// Plain HTTP listener that redirects every request to HTTPS on `https_port`.
+async fn serve_https_redirect(
+    shutdown: &utils::shutdown::Shutdown,
+    addr: SocketAddr,
+    https_port: u16,
+) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("redirecting http://{} to HTTPS", addr);
+    let server = axum::serve(listener, routes::redirect::router(https_port))
+        .with_graceful_shutdown(shutdown.token().cancelled_owned());
+    shutdown.spawn(async move {
+        if let Err(e) = server.await {
+            tracing::error!(error = %e, "HTTPS redirect listener failed");
+        }
+    });
+    Ok(())
+}
+
+fn init_tracing(cfg: &Config) -> anyhow::Result<utils::telemetry::Telemetry> {
+    use tracing_subscriber::{
+        filter::LevelFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer,
+    };
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    fmt()
-        .with_max_level(Level::INFO)
-        .with_ansi(true)
-        .with_env_filter(filter)
+    let output = match cfg.log_format {
+        config::LogFormat::Text => fmt::layer().with_ansi(true).boxed(),
+        // Every enclosing span is listed, so nested lines keep the request ID
+        config::LogFormat::Json => fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(true)
+            .boxed(),
+    };
+    let (telemetry, tracer) = utils::telemetry::init(cfg)?;
+    tracing_subscriber::registry()
+        .with(LevelFilter::from_level(Level::INFO))
+        .with(filter)
+        .with(output)
+        .with(tracer.map(|t| tracing_opentelemetry::layer().with_tracer(t)))
         .init();
-}
\ No newline at end of file
+    Ok(telemetry)
+}
@@ -1,40 +1,316 @@
+use crate::{
+    app_middleware::time_zone::requested_zone,
+    models::{access_token::Scope, user::Role},
+    services::{
+        access_token_service::{self, TOKEN_PREFIX},
+        auth_service, session_service,
+    },
+    utils::{
+        jwt::{validate_token, Claims},
+        session_cookie::{csrf_matches, CSRF_COOKIE, CSRF_HEADER, SESSION_COOKIE},
+        time_zone,
+    },
+    AppState,
+};
 use axum::{
     body::Body,
     extract::State,
-    http::{Request, StatusCode},
+    http::{Method, Request, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
+    Json,
 };
-use crate::{
-    utils::jwt::validate_token,
-    AppState,
-};
-use crate::db::redis::is_token_blacklisted;
+use tower_cookies::Cookies;
 
 #[derive(Clone, Copy)]
-pub struct CurrentUser(pub uuid::Uuid);
+pub struct CurrentUser {
+    pub id: uuid::Uuid,
+    pub role: Role,
+    /// Stored time zone preference
+    pub time_zone: Option<chrono_tz::Tz>,
+}
+
+/// How the current request was authenticated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthMethod {
+    /// Interactive login token (JWT)
+    Session,
+    /// Personal access token
+    AccessToken,
+}
+
+/// Inserted by [`require_auth`] next to [`CurrentUser`].
+#[derive(Clone, Debug)]
+pub struct Authentication {
+    pub method: AuthMethod,
+    pub scopes: Vec<Scope>,
+    /// Claims of the session token; `None` for personal access tokens
+    pub claims: Option<Claims>,
+}
+
+impl Authentication {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+/// Session cookie sent by a browser; state-changing requests must echo the
+/// CSRF cookie in the `X-CSRF-Token` header.
+fn cookie_token(req: &Request<Body>) -> Result<Option<String>, StatusCode> {
+    let cookies = match req.extensions().get::<Cookies>() {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+    let session = match cookies.get(SESSION_COOKIE) {
+        Some(c) => c.value().to_string(),
+        None => return Ok(None),
+    };
+    if !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        let header = req
+            .headers()
+            .get(CSRF_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("");
+        let cookie = cookies.get(CSRF_COOKIE);
+        if !cookie.is_some_and(|c| csrf_matches(c.value(), header)) {
+            return Err(StatusCode::FORBIDDEN);
+        }
+    }
+    Ok(Some(session))
+}
+
+/// Loads the role and rejects disabled accounts. Accounts with a forced password
+/// reset get a 403 explaining why, unless the route is the password change itself.
+async fn check_account(
+    state: &AppState,
+    user_id: uuid::Uuid,
+    allow_password_reset: bool,
+) -> Result<Result<CurrentUser, Response>, StatusCode> {
+    let status = match auth_service::account_status(state, user_id).await {
+        Ok(Some(status)) => status,
+        Ok(None) => return Err(StatusCode::UNAUTHORIZED),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    if status.disabled {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    if status.password_reset_required && !allow_password_reset {
+        let body = Json(serde_json::json!({
+            "error": "password reset required",
+            "password_reset_required": true,
+        }));
+        return Ok(Err((StatusCode::FORBIDDEN, body).into_response()));
+    }
+    Ok(Ok(CurrentUser {
+        id: user_id,
+        role: status.role,
+        time_zone: status.time_zone,
+    }))
+}
 
-pub async fn require_auth(State(state): State<AppState>, mut req: Request<Body>, next: Next) -> Result<Response, StatusCode> {
-    let auth = req.headers().get(axum::http::header::AUTHORIZATION)
+/// Runs the rest of the request with timestamps rendered in the `Accept-Timezone`
+/// zone if given, else the user's preference, else the configured default.
+async fn run_in_time_zone(
+    state: &AppState,
+    req: Request<Body>,
+    next: Next,
+    current: CurrentUser,
+) -> Response {
+    // The current span is the request's `http.request` span
+    tracing::Span::current().record("user_id", tracing::field::display(current.id));
+    let zone = match requested_zone(req.headers()) {
+        Ok(Some(zone)) => zone,
+        Ok(None) => current.time_zone.unwrap_or(state.config.default_time_zone),
+        Err(rejection) => return rejection.into_response(),
+    };
+    time_zone::scope(zone, next.run(req)).await
+}
+
+async fn authenticate(
+    state: AppState,
+    mut req: Request<Body>,
+    next: Next,
+    allow_password_reset: bool,
+) -> Result<Response, StatusCode> {
+    let auth = req
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
-    if !auth.starts_with("Bearer ") {
-        return Err(StatusCode::UNAUTHORIZED);
+    // An explicit Authorization header wins over the session cookie
+    let token = if auth.starts_with("Bearer ") {
+        auth.trim_start_matches("Bearer ").trim().to_string()
+    } else {
+        cookie_token(&req)?.ok_or(StatusCode::UNAUTHORIZED)?
+    };
+    let token = token.as_str();
+
+    if token.starts_with(TOKEN_PREFIX) {
+        let pat = match access_token_service::authenticate(&state, token).await {
+            Ok(Some(pat)) => pat,
+            Ok(None) => return Err(StatusCode::UNAUTHORIZED),
+            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+        };
+        tracing::debug!(user_id = %pat.user_id, token_id = %pat.id, "authenticated with personal access token");
+        let current = match check_account(&state, pat.user_id, allow_password_reset).await? {
+            Ok(current) => current,
+            Err(rejection) => return Ok(rejection),
+        };
+        req.extensions_mut().insert(current);
+        req.extensions_mut().insert(Authentication {
+            method: AuthMethod::AccessToken,
+            scopes: pat.scopes,
+            claims: None,
+        });
+        return Ok(run_in_time_zone(&state, req, next, current).await);
     }
-    let token = auth.trim_start_matches("Bearer ").trim();
 
-    let claims = match validate_token(token, &state.jwt_secret) {
+    let claims = match validate_token(token, &state.jwt_keys) {
         Ok(c) => c,
         Err(_) => return Err(StatusCode::UNAUTHORIZED),
     };
 
-    // Check blacklist
-    match is_token_blacklisted(&state.redis, &claims.jti).await {
+    // Check blacklist; with Redis down this depends on REDIS_FAILURE_POLICY
+    match state
+        .revocations
+        .is_revoked(&state.redis, &claims.jti, claims.exp as i64)
+        .await
+    {
         Ok(true) => return Err(StatusCode::UNAUTHORIZED),
         Ok(false) => {}
-        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(_) => return Err(StatusCode::SERVICE_UNAVAILABLE),
     }
+    // Last-seen tracking is informational; don't fail the request over it, and
+    // don't wait on Redis timeouts while the reconnect task reports it down
+    if state.revocations.is_redis_up() {
+        if let Err(e) = session_service::touch_session(&state, &claims.jti).await {
+            tracing::warn!(error = %e, "failed to update session last seen");
+        }
+    }
+
+    let current = match check_account(&state, claims.sub, allow_password_reset).await? {
+        Ok(current) => current,
+        Err(rejection) => return Ok(rejection),
+    };
+    req.extensions_mut().insert(current);
+    req.extensions_mut().insert(Authentication {
+        method: AuthMethod::Session,
+        scopes: Scope::ALL.to_vec(),
+        claims: Some(claims),
+    });
+    Ok(run_in_time_zone(&state, req, next, current).await)
+}
+
+pub async fn require_auth(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    authenticate(state, req, next, false).await
+}
+
+/// Like [`require_auth`] but also admits accounts that must change their password.
+pub async fn require_auth_allow_password_reset(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    authenticate(state, req, next, true).await
+}
+
+/// Note routes: reads need `notes:read`, everything else `notes:write`.
+/// Must run after [`require_auth`].
+pub async fn require_note_scopes(req: Request<Body>, next: Next) -> Result<Response, StatusCode> {
+    let required = match *req.method() {
+        Method::GET | Method::HEAD => Scope::NotesRead,
+        _ => Scope::NotesWrite,
+    };
+    match req.extensions().get::<Authentication>() {
+        Some(auth) if auth.has_scope(required) => Ok(next.run(req).await),
+        Some(_) => Err(StatusCode::FORBIDDEN),
+        None => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Account management routes are not reachable with personal access tokens.
+/// Must run after [`require_auth`].
+pub async fn require_session(req: Request<Body>, next: Next) -> Result<Response, StatusCode> {
+    match req.extensions().get::<Authentication>() {
+        Some(auth) if auth.method == AuthMethod::Session => Ok(next.run(req).await),
+        Some(_) => Err(StatusCode::FORBIDDEN),
+        None => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Administration routes. Must run after [`require_auth`].
+pub async fn require_admin(req: Request<Body>, next: Next) -> Result<Response, StatusCode> {
+    match req.extensions().get::<CurrentUser>() {
+        Some(user) if user.role == Role::Admin => Ok(next.run(req).await),
+        Some(_) => Err(StatusCode::FORBIDDEN),
+        None => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_cookies::Cookie;
 
-    req.extensions_mut().insert(CurrentUser(claims.sub));
-    Ok(next.run(req).await)
-}
\ No newline at end of file
+    /// A request carrying the given cookies and `X-CSRF-Token` header.
+    fn request(
+        method: Method,
+        cookies: &[(&'static str, &'static str)],
+        header: Option<&str>,
+    ) -> Request<Body> {
+        let mut builder = Request::builder().method(method).uri("/notes");
+        if let Some(header) = header {
+            builder = builder.header(CSRF_HEADER, header);
+        }
+        let mut req = builder.body(Body::empty()).unwrap();
+        let jar = Cookies::default();
+        for (name, value) in cookies {
+            jar.add(Cookie::new(*name, *value));
+        }
+        req.extensions_mut().insert(jar);
+        req
+    }
+
+    const SESSION: (&str, &str) = (SESSION_COOKIE, "session-jwt");
+    const CSRF: (&str, &str) = (CSRF_COOKIE, "csrf-token");
+
+    #[test]
+    fn state_changing_requests_need_the_csrf_header() {
+        for method in [Method::POST, Method::PUT, Method::PATCH, Method::DELETE] {
+            let ok = request(method.clone(), &[SESSION, CSRF], Some("csrf-token"));
+            assert_eq!(cookie_token(&ok), Ok(Some("session-jwt".to_string())));
+
+            let missing = request(method.clone(), &[SESSION, CSRF], None);
+            assert_eq!(cookie_token(&missing), Err(StatusCode::FORBIDDEN));
+
+            let mismatched = request(method.clone(), &[SESSION, CSRF], Some("other-token"));
+            assert_eq!(cookie_token(&mismatched), Err(StatusCode::FORBIDDEN));
+
+            // Without the CSRF cookie no header value can match
+            let no_cookie = request(method, &[SESSION], Some("csrf-token"));
+            assert_eq!(cookie_token(&no_cookie), Err(StatusCode::FORBIDDEN));
+        }
+    }
+
+    #[test]
+    fn safe_methods_are_exempt() {
+        for method in [Method::GET, Method::HEAD, Method::OPTIONS] {
+            let req = request(method, &[SESSION], None);
+            assert_eq!(cookie_token(&req), Ok(Some("session-jwt".to_string())));
+        }
+    }
+
+    #[test]
+    fn no_session_cookie_means_no_cookie_token() {
+        let req = request(Method::POST, &[CSRF], None);
+        assert_eq!(cookie_token(&req), Ok(None));
+
+        let mut req = request(Method::POST, &[], None);
+        req.extensions_mut().remove::<Cookies>();
+        assert_eq!(cookie_token(&req), Ok(None));
+    }
+}
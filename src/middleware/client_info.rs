@@ -0,0 +1,144 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+use ipnet::IpNet;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::AppState;
+
+/// Describes the device a request comes from; recorded with each login session.
+#[derive(Clone, Debug, Default)]
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    /// Optional label sent by the client in `X-Device-Name`
+    pub device_name: Option<String>,
+}
+
+fn header(parts: &Parts, name: &str) -> Option<String> {
+    parts
+        .headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// The client address as seen by the first proxy we trust.
+///
+/// `X-Forwarded-For` is only read when the peer is a trusted proxy. Each
+/// proxy appends the address it received the request from, so the list is
+/// walked from the right, skipping trusted proxies; entries left of the
+/// first untrusted hop were written by the client and may be forged.
+fn client_ip(peer: IpAddr, forwarded_for: &[&str], trusted: &[IpNet]) -> IpAddr {
+    let is_trusted = |ip: &IpAddr| trusted.iter().any(|net| net.contains(ip));
+    let mut client = peer.to_canonical();
+    if !is_trusted(&client) {
+        return client;
+    }
+    for hop in forwarded_for.iter().flat_map(|v| v.split(',')).rev() {
+        // A malformed entry cannot be attributed; keep the proxy that forwarded it
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = ip.to_canonical();
+        if !is_trusted(&client) {
+            break;
+        }
+    }
+    client
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let forwarded_for: Vec<&str> = parts
+            .headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .collect();
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| {
+                client_ip(addr.ip(), &forwarded_for, &state.config.trusted_proxies).to_string()
+            });
+        Ok(ClientInfo {
+            ip,
+            user_agent: header(parts, USER_AGENT.as_str()).map(|ua| ua.chars().take(512).collect()),
+            device_name: header(parts, "x-device-name").map(|n| n.chars().take(64).collect()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|n| n.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn ignores_forwarded_for_from_untrusted_peers() {
+        let trusted = nets(&["10.0.0.0/8"]);
+        assert_eq!(
+            client_ip(ip("203.0.113.9"), &["1.2.3.4"], &trusted),
+            ip("203.0.113.9")
+        );
+        assert_eq!(client_ip(ip("10.0.0.2"), &["1.2.3.4"], &[]), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn takes_the_right_most_untrusted_hop() {
+        let trusted = nets(&["10.0.0.0/8", "192.0.2.1/32"]);
+        // The client prepended a forged entry; the proxies appended the real one
+        let header = ["6.6.6.6, 198.51.100.7, 192.0.2.1"];
+        assert_eq!(
+            client_ip(ip("10.0.0.2"), &header, &trusted),
+            ip("198.51.100.7")
+        );
+        // Entries may be split across several headers
+        let headers = ["6.6.6.6", "198.51.100.7,192.0.2.1"];
+        assert_eq!(
+            client_ip(ip("10.0.0.2"), &headers, &trusted),
+            ip("198.51.100.7")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_last_trusted_hop() {
+        let trusted = nets(&["10.0.0.0/8"]);
+        assert_eq!(client_ip(ip("10.0.0.2"), &[], &trusted), ip("10.0.0.2"));
+        assert_eq!(
+            client_ip(ip("10.0.0.2"), &["10.1.1.1"], &trusted),
+            ip("10.1.1.1")
+        );
+        assert_eq!(
+            client_ip(ip("10.0.0.2"), &["not-an-ip, 10.1.1.1"], &trusted),
+            ip("10.1.1.1")
+        );
+    }
+
+    #[test]
+    fn compares_ipv4_mapped_addresses_as_ipv4() {
+        let trusted = nets(&["127.0.0.1/32"]);
+        assert_eq!(
+            client_ip(ip("::ffff:127.0.0.1"), &["::ffff:198.51.100.7"], &trusted),
+            ip("198.51.100.7")
+        );
+    }
+}
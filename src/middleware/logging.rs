@@ -1,24 +1,85 @@
-use axum::{
-    body::Body,
-    http::Request,
-    middleware::Next,
-    response::Response,
-};
+use std::time::Instant;
+
+use axum::{body::Body, http::Request, middleware::Next, response::Response};
 use tracing::info;
 
+/// Query parameters whose values are credentials or one-time codes (OIDC
+/// callbacks, email links) and must not end up in the logs.
+const SENSITIVE_PARAMS: &[&str] = &[
+    "code",
+    "state",
+    "token",
+    "access_token",
+    "id_token",
+    "refresh_token",
+    "password",
+];
+
+/// Replaces the values of [`SENSITIVE_PARAMS`] with `REDACTED`, keeping the
+/// parameter names and everything else as sent.
+fn redact_query(query: &str) -> String {
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _))
+                if SENSITIVE_PARAMS
+                    .iter()
+                    .any(|p| p.eq_ignore_ascii_case(name)) =>
+            {
+                format!("{name}=REDACTED")
+            }
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Logs the request and its response. Both lines are emitted inside the
+/// `http.request` span, which carries the request ID and, once
+/// authenticated, the user ID.
 pub async fn request_logger(req: Request<Body>, next: Next) -> Response {
+    let started = Instant::now();
     let method = req.method().clone();
     let uri = req.uri().clone();
-    let query = uri.query().unwrap_or("");
+    let query = redact_query(uri.query().unwrap_or(""));
     let content_type = req
         .headers()
         .get(axum::http::header::CONTENT_TYPE)
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
 
-    info!(target: "http", "request: method={} path={} query={} ct={}", method, uri.path(), query, content_type);
+    info!(target: "http", method = %method, path = uri.path(), query = %query, content_type, "request");
     let res = next.run(req).await;
     let status = res.status();
-    info!(target: "http", "response: status={} reason={}", status.as_u16(), status.canonical_reason().unwrap_or(""));
+    // Milliseconds with microsecond precision
+    let latency_ms = (started.elapsed().as_secs_f64() * 1_000_000.0).round() / 1000.0;
+    info!(
+        target: "http",
+        status = status.as_u16(),
+        reason = status.canonical_reason().unwrap_or(""),
+        latency_ms,
+        "response"
+    );
     res
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sensitive_values_are_redacted() {
+        assert_eq!(
+            redact_query("code=abc123&state=xyz&page=2"),
+            "code=REDACTED&state=REDACTED&page=2"
+        );
+        assert_eq!(redact_query("TOKEN=secret"), "TOKEN=REDACTED");
+    }
+
+    #[test]
+    fn other_parameters_are_kept() {
+        assert_eq!(redact_query(""), "");
+        assert_eq!(redact_query("q=notes&tag"), "q=notes&tag");
+        assert_eq!(redact_query("codes=1"), "codes=1");
+    }
+}
@@ -0,0 +1,54 @@
+use std::time::Instant;
+
+use axum::{body::Body, extract::MatchedPath, http::Request, middleware::Next, response::Response};
+
+/// Route label for requests that matched no route, so unknown paths don't
+/// create a new time series each.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Holds one slot of `http_requests_in_flight` and gives it back on drop, so
+/// requests whose future is cancelled (client disconnect, timeout) are not
+/// counted forever.
+struct InFlight(metrics::Gauge);
+
+impl InFlight {
+    fn start() -> Self {
+        let gauge = metrics::gauge!("http_requests_in_flight");
+        gauge.increment(1.0);
+        Self(gauge)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.decrement(1.0);
+    }
+}
+
+/// Counts requests and their latency per method, route template and status.
+///
+/// The route label is the matched template (`/api/tiny-note/notes/:id`), not
+/// the raw path, to keep the number of series bounded.
+pub async fn track_requests(req: Request<Body>, next: Next) -> Response {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+
+    let in_flight = InFlight::start();
+    let res = next.run(req).await;
+    drop(in_flight);
+
+    let labels = [
+        ("method", method),
+        ("route", route),
+        ("status", res.status().as_u16().to_string()),
+    ];
+    metrics::counter!("http_requests_total", &labels).increment(1);
+    metrics::histogram!("http_request_duration_seconds", &labels)
+        .record(started.elapsed().as_secs_f64());
+    res
+}
@@ -1,2 +1,9 @@
 pub mod auth_middleware;
-pub mod logging;
\ No newline at end of file
+pub mod client_info;
+pub mod cors;
+pub mod logging;
+pub mod metrics;
+pub mod request_id;
+pub mod time_zone;
+pub mod trace_context;
+pub mod validated_json;
@@ -0,0 +1,43 @@
+use axum::{
+    body::Body,
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// The ID of the current request, available as a request extension.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Takes the request ID from `X-Request-Id` (e.g. set by a proxy) or generates
+/// one, and echoes it in the response. IDs that are too long or contain other
+/// characters than `[A-Za-z0-9._:-]` are replaced, so they can't forge log lines.
+pub async fn set_request_id(mut req: Request<Body>, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| is_valid(v))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    let mut res = next.run(req).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    res
+}
+
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
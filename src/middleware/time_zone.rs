@@ -0,0 +1,53 @@
+use axum::{
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono_tz::Tz;
+
+use crate::{
+    utils::time_zone::{self, TIME_ZONE_HEADER},
+    AppState,
+};
+
+/// The `Accept-Timezone` header named something that isn't an IANA time zone.
+pub struct UnknownTimeZone;
+
+impl IntoResponse for UnknownTimeZone {
+    fn into_response(self) -> Response {
+        let body =
+            Json(serde_json::json!({ "error": "unknown time zone in Accept-Timezone header" }));
+        (StatusCode::BAD_REQUEST, body).into_response()
+    }
+}
+
+/// Zone from the `Accept-Timezone` header, if any.
+pub fn requested_zone(headers: &HeaderMap) -> Result<Option<Tz>, UnknownTimeZone> {
+    match headers.get(TIME_ZONE_HEADER) {
+        Some(value) => value
+            .to_str()
+            .ok()
+            .and_then(time_zone::parse)
+            .map(Some)
+            .ok_or(UnknownTimeZone),
+        None => Ok(None),
+    }
+}
+
+/// Renders timestamps in the `Accept-Timezone` zone, else `DEFAULT_TIME_ZONE`.
+/// Covers routes without a signed-in user; `require_auth` opens an inner scope
+/// that also considers the user's preference.
+pub async fn scope_time_zone(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let zone = match requested_zone(req.headers()) {
+        Ok(zone) => zone.unwrap_or(state.config.default_time_zone),
+        Err(rejection) => return rejection.into_response(),
+    };
+    time_zone::scope(zone, next.run(req)).await
+}
@@ -0,0 +1,156 @@
+use axum::{
+    body::Body,
+    extract::MatchedPath,
+    http::{HeaderMap, Request},
+    middleware::Next,
+    response::Response,
+};
+use opentelemetry::propagation::Extractor;
+
+use super::request_id::RequestId;
+use tracing::{field::Empty, Instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+/// Runs each request in an `http.request` server span. A W3C `traceparent`
+/// (and `tracestate`) sent by the caller makes it a child of the caller's trace.
+///
+/// The span carries the request ID (set by `set_request_id`, which must run
+/// first) and gets `user_id` once the auth middleware identified the caller.
+pub async fn trace_requests(req: Request<Body>, next: Next) -> Response {
+    let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(req.headers()))
+    });
+    let method = req.method().clone();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string());
+    let span = tracing::info_span!(
+        "http.request",
+        otel.name = %match &route {
+            Some(route) => format!("{} {}", method, route),
+            None => method.to_string(),
+        },
+        otel.kind = "server",
+        otel.status_code = Empty,
+        http.request.method = %method,
+        http.route = route,
+        url.path = req.uri().path(),
+        http.response.status_code = Empty,
+        request_id = req.extensions().get::<RequestId>().map(|id| id.0.as_str()),
+        user_id = Empty,
+    );
+    span.set_parent(parent);
+
+    let res = next.run(req).instrument(span.clone()).await;
+    let status = res.status();
+    span.record("http.response.status_code", status.as_u16());
+    if status.is_server_error() {
+        span.record("otel.status_code", "ERROR");
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        body::Bytes,
+        routing::{get, post},
+        Router,
+    };
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_proto::tonic::{
+        collector::trace::v1::ExportTraceServiceRequest, trace::v1::span::SpanKind,
+    };
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use prost::Message;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    async fn serve(app: Router) -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        addr
+    }
+
+    /// The subscriber is installed for this thread only, so the test relies on
+    /// `#[tokio::test]`'s current-thread runtime to run the server's tasks here.
+    #[tokio::test]
+    async fn exported_span_is_child_of_incoming_traceparent() {
+        let exports: Arc<Mutex<Vec<ExportTraceServiceRequest>>> = Arc::default();
+        let collector = Router::new().route(
+            "/v1/traces",
+            post({
+                let exports = exports.clone();
+                move |body: Bytes| async move {
+                    exports
+                        .lock()
+                        .unwrap()
+                        .push(ExportTraceServiceRequest::decode(body).unwrap());
+                }
+            }),
+        );
+        let collector = serve(collector).await;
+
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        let provider =
+            crate::utils::telemetry::build_provider(&format!("http://{collector}"), 1.0, "test")
+                .unwrap();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = Router::new()
+            .route("/notes/:id", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn(super::trace_requests));
+        let app = serve(app).await;
+        let status = reqwest::Client::new()
+            .get(format!("http://{app}/notes/1"))
+            .header("traceparent", format!("00-{TRACE_ID}-{PARENT_SPAN_ID}-01"))
+            .send()
+            .await
+            .unwrap()
+            .status();
+        assert_eq!(status, 200);
+
+        // Shutdown flushes the batch and blocks until the export finished
+        tokio::task::spawn_blocking(move || provider.shutdown().unwrap())
+            .await
+            .unwrap();
+        let exports = exports.lock().unwrap();
+        let span = exports
+            .iter()
+            .flat_map(|e| &e.resource_spans)
+            .flat_map(|r| &r.scope_spans)
+            .flat_map(|s| &s.spans)
+            .find(|s| s.name == "GET /notes/:id")
+            .expect("server span was exported");
+        assert_eq!(span.trace_id, hex(TRACE_ID));
+        assert_eq!(span.parent_span_id, hex(PARENT_SPAN_ID));
+        assert_eq!(span.kind, SpanKind::Server as i32);
+    }
+}
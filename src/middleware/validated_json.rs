@@ -0,0 +1,113 @@
+use axum::{
+    async_trait,
+    extract::{rejection::JsonRejection, FromRequest, Request},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};
+
+use crate::models::validation::FieldError;
+
+/// Like [`Json`], but also runs the model's `#[validate(...)]` rules.
+///
+/// Invalid bodies are answered with `{"error": ..., "details": [{field, code, message}]}`:
+/// `422` for rule violations, and the status of the underlying rejection for
+/// malformed JSON.
+pub struct ValidatedJson<T>(pub T);
+
+pub struct ValidationRejection {
+    status: StatusCode,
+    error: &'static str,
+    details: Vec<FieldError>,
+}
+
+impl ValidationRejection {
+    pub fn new(status: StatusCode, error: &'static str, details: Vec<FieldError>) -> Self {
+        ValidationRejection {
+            status,
+            error,
+            details,
+        }
+    }
+}
+
+impl IntoResponse for ValidationRejection {
+    fn into_response(self) -> Response {
+        tracing::info!(target: "http", status = self.status.as_u16(), details = ?self.details, "request rejected: {}", self.error);
+        let body = serde_json::json!({ "error": self.error, "details": self.details });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ValidationRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(json_rejection)?;
+        value.validate().map_err(|errors| {
+            ValidationRejection::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "validation failed",
+                field_errors(&errors),
+            )
+        })?;
+        Ok(ValidatedJson(value))
+    }
+}
+
+fn json_rejection(rejection: JsonRejection) -> ValidationRejection {
+    let code = match &rejection {
+        JsonRejection::JsonDataError(_) => "invalid_data",
+        JsonRejection::JsonSyntaxError(_) => "invalid_json",
+        JsonRejection::MissingJsonContentType(_) => "missing_content_type",
+        _ => "invalid_body",
+    };
+    let details = vec![FieldError::new("body", code, rejection.body_text())];
+    ValidationRejection::new(rejection.status(), "invalid request body", details)
+}
+
+/// Flattens validator errors into one entry per violated rule; nested fields
+/// are reported with dotted paths such as `items[0].name`.
+fn field_errors(errors: &ValidationErrors) -> Vec<FieldError> {
+    let mut out = Vec::new();
+    collect(errors, "", &mut out);
+    out.sort_by(|a, b| a.field.cmp(&b.field));
+    out
+}
+
+fn collect(errors: &ValidationErrors, prefix: &str, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", prefix, field)
+        };
+        match kind {
+            ValidationErrorsKind::Field(list) => {
+                for e in list {
+                    let message = e
+                        .message
+                        .as_ref()
+                        .map(|m| m.to_string())
+                        .unwrap_or_else(|| format!("is invalid ({})", e.code));
+                    out.push(FieldError::new(&path, &e.code, message));
+                }
+            }
+            ValidationErrorsKind::Struct(inner) => collect(inner, &path, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, inner) in items {
+                    collect(inner, &format!("{}[{}]", path, index), out);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{mysql::MySqlRow, Row};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use super::utc_datetime;
+
+/// Longest lifetime a personal access token can be created with.
+pub const MAX_EXPIRES_IN_DAYS: i64 = 3650;
+
+fn valid_expiry(days: i64) -> Result<(), ValidationError> {
+    if !(1..=MAX_EXPIRES_IN_DAYS).contains(&days) {
+        let message = format!("must be between 1 and {}", MAX_EXPIRES_IN_DAYS);
+        return Err(ValidationError::new("range").with_message(message.into()));
+    }
+    Ok(())
+}
+
+/// Permission granted to a personal access token. Session tokens implicitly hold all scopes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    NotesRead,
+    NotesWrite,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 2] = [Scope::NotesRead, Scope::NotesWrite];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::NotesRead => "notes:read",
+            Scope::NotesWrite => "notes:write",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Scope> {
+        match s.trim() {
+            "notes:read" | "read-only" => Some(Scope::NotesRead),
+            "notes:write" => Some(Scope::NotesWrite),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AccessTokenInfo {
+    pub id: Uuid,
+    pub name: String,
+    /// First characters of the token, to help users recognise it
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize_opt")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize_opt")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct CreateAccessTokenRequest {
+    #[validate(length(min = 1, max = 64, message = "must be 1-64 characters"))]
+    pub name: String,
+    /// Defaults to all scopes; `["read-only"]` is accepted as an alias for `["notes:read"]`
+    pub scopes: Option<Vec<String>>,
+    /// Defaults to a token that never expires
+    #[validate(custom(function = "valid_expiry"))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CreatedAccessToken {
+    /// Plaintext token, shown only once
+    pub token: String,
+    pub info: AccessTokenInfo,
+}
+
+impl<'r> sqlx::FromRow<'r, MySqlRow> for AccessTokenInfo {
+    fn from_row(row: &'r MySqlRow) -> Result<Self, sqlx::Error> {
+        let scopes: String = row.try_get("scopes")?;
+        let expires_naive: Option<NaiveDateTime> = row.try_get("expires_at")?;
+        let last_used_naive: Option<NaiveDateTime> = row.try_get("last_used_at")?;
+        let created_naive: NaiveDateTime = row.try_get("created_at")?;
+        Ok(AccessTokenInfo {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+            token_prefix: row.try_get("token_prefix")?,
+            scopes: scopes
+                .split(',')
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect(),
+            expires_at: expires_naive.map(utc_datetime),
+            last_used_at: last_used_naive.map(utc_datetime),
+            created_at: utc_datetime(created_naive),
+        })
+    }
+}
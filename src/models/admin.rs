@@ -0,0 +1,66 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{mysql::MySqlRow, Row};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{user::Role, utc_datetime};
+
+/// A user as seen by administrators.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AdminUserInfo {
+    pub id: Uuid,
+    pub username: String,
+    pub email: String,
+    pub role: Role,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize_opt")]
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub password_reset_required: bool,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AdminUserQuery {
+    /// Matches username or email
+    pub q: Option<String>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AdminUserList {
+    pub users: Vec<AdminUserInfo>,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UsageStats {
+    pub users_total: i64,
+    pub users_disabled: i64,
+    pub admins: i64,
+    pub users_created_last_7_days: i64,
+    pub notes_total: i64,
+    pub notes_updated_last_7_days: i64,
+    pub active_access_tokens: i64,
+}
+
+impl<'r> sqlx::FromRow<'r, MySqlRow> for AdminUserInfo {
+    fn from_row(row: &'r MySqlRow) -> Result<Self, sqlx::Error> {
+        let role: String = row.try_get("role")?;
+        let disabled_naive: Option<NaiveDateTime> = row.try_get("disabled_at")?;
+        let created_naive: NaiveDateTime = row.try_get("created_at")?;
+        Ok(AdminUserInfo {
+            id: row.try_get("id")?,
+            username: row.try_get("username")?,
+            email: row.try_get("email")?,
+            role: Role::parse(&role),
+            disabled_at: disabled_naive.map(utc_datetime),
+            password_reset_required: row.try_get("password_reset_required")?,
+            created_at: utc_datetime(created_naive),
+        })
+    }
+}
@@ -0,0 +1,102 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{mysql::MySqlRow, Row};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::utc_datetime;
+
+/// Kind of security event stored in `audit_events.event_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEventType {
+    Login,
+    Logout,
+    PasswordChange,
+    SessionRevoke,
+    AccessTokenCreate,
+    AccessTokenRevoke,
+    TotpEnable,
+    TotpDisable,
+    RecoveryCodesRegenerate,
+    PasskeyAdd,
+    PasskeyRemove,
+    AccountDisable,
+    AccountEnable,
+    PasswordResetForce,
+}
+
+impl AuditEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEventType::Login => "login",
+            AuditEventType::Logout => "logout",
+            AuditEventType::PasswordChange => "password_change",
+            AuditEventType::SessionRevoke => "session_revoke",
+            AuditEventType::AccessTokenCreate => "access_token_create",
+            AuditEventType::AccessTokenRevoke => "access_token_revoke",
+            AuditEventType::TotpEnable => "totp_enable",
+            AuditEventType::TotpDisable => "totp_disable",
+            AuditEventType::RecoveryCodesRegenerate => "recovery_codes_regenerate",
+            AuditEventType::PasskeyAdd => "passkey_add",
+            AuditEventType::PasskeyRemove => "passkey_remove",
+            AuditEventType::AccountDisable => "account_disable",
+            AuditEventType::AccountEnable => "account_enable",
+            AuditEventType::PasswordResetForce => "password_reset_force",
+        }
+    }
+}
+
+/// How a login was performed, recorded in the event details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginMethod {
+    Password,
+    Totp,
+    Passkey,
+    Oidc,
+}
+
+impl LoginMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginMethod::Password => "password",
+            LoginMethod::Totp => "totp",
+            LoginMethod::Passkey => "passkey",
+            LoginMethod::Oidc => "oidc",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub event_type: String,
+    pub success: bool,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub details: Option<serde_json::Value>,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditEventQuery {
+    /// Defaults to 50, at most 200
+    pub limit: Option<u32>,
+}
+
+impl<'r> sqlx::FromRow<'r, MySqlRow> for AuditEvent {
+    fn from_row(row: &'r MySqlRow) -> Result<Self, sqlx::Error> {
+        let details: Option<String> = row.try_get("details")?;
+        let created_naive: NaiveDateTime = row.try_get("created_at")?;
+        Ok(AuditEvent {
+            id: row.try_get("id")?,
+            event_type: row.try_get("event_type")?,
+            success: row.try_get("success")?,
+            ip: row.try_get("ip")?,
+            user_agent: row.try_get("user_agent")?,
+            details: details.and_then(|d| serde_json::from_str(&d).ok()),
+            created_at: utc_datetime(created_naive),
+        })
+    }
+}
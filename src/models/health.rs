@@ -0,0 +1,53 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Up,
+    Down,
+}
+
+/// Result of pinging one dependency.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DependencyCheck {
+    pub status: CheckStatus,
+    pub latency_ms: u64,
+    /// Why the check failed: `unavailable` or `timed out`; details are only logged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PoolStats {
+    /// Open connections, idle or in use
+    pub size: u32,
+    pub idle: u32,
+    pub in_use: u32,
+    pub max: u32,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MySqlCheck {
+    #[serde(flatten)]
+    pub check: DependencyCheck,
+    pub pool: PoolStats,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DependencyChecks {
+    pub mysql: MySqlCheck,
+    pub redis: DependencyCheck,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Readiness {
+    /// `up` only when every dependency is up
+    pub status: CheckStatus,
+    pub checks: DependencyChecks,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Liveness {
+    pub status: CheckStatus,
+}
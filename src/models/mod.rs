@@ -1,2 +1,24 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+pub mod access_token;
+pub mod admin;
+pub mod audit;
+pub mod health;
+pub mod note;
+pub mod oidc;
+pub mod passkey;
+pub mod preferences;
+pub mod session;
+pub mod totp;
 pub mod user;
-pub mod note;
\ No newline at end of file
+pub mod validation;
+
+/// Stored `DATETIME` columns hold UTC.
+pub(crate) fn utc_datetime(naive: NaiveDateTime) -> DateTime<Utc> {
+    naive.and_utc()
+}
+
+/// Converts a Unix timestamp (as stored in Redis).
+pub(crate) fn utc_from_timestamp(ts: i64) -> Option<DateTime<Utc>> {
+    DateTime::<Utc>::from_timestamp(ts, 0)
+}
@@ -1,9 +1,16 @@
-use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{mysql::MySqlRow, Row};
+use utoipa::ToSchema;
 use uuid::Uuid;
+use validator::Validate;
 
-#[derive(Debug, Clone, Serialize)]
+use super::{
+    utc_datetime,
+    validation::{fits_text_column, not_blank},
+};
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct Note {
     pub id: Uuid,
     pub user_id: Uuid,
@@ -11,46 +18,48 @@ pub struct Note {
     pub content: String,
     pub category: Option<String>,
     pub tags: Option<String>, // comma-separated tags, simple approach
-    pub created_at: DateTime<FixedOffset>,
-    pub updated_at: DateTime<FixedOffset>,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize")]
+    pub created_at: DateTime<Utc>,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize")]
+    pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
 pub struct CreateNoteRequest {
+    #[validate(
+        custom(function = "not_blank"),
+        length(max = 255, message = "must be at most 255 characters")
+    )]
     pub title: String,
+    #[validate(custom(function = "fits_text_column"))]
     pub content: String,
+    #[validate(length(max = 64, message = "must be at most 64 characters"))]
     pub category: String,
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
     pub tags: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Absent fields are left unchanged; present ones follow the same rules as on create.
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
 pub struct UpdateNoteRequest {
+    #[validate(
+        custom(function = "not_blank"),
+        length(max = 255, message = "must be at most 255 characters")
+    )]
     pub title: Option<String>,
+    #[validate(custom(function = "fits_text_column"))]
     pub content: Option<String>,
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
     pub tags: Option<String>,
+    #[validate(length(max = 64, message = "must be at most 64 characters"))]
     pub category: Option<String>,
 }
 
 // Manual FromRow for Note
 impl<'r> sqlx::FromRow<'r, MySqlRow> for Note {
     fn from_row(row: &'r MySqlRow) -> Result<Self, sqlx::Error> {
-        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
         let created_naive: NaiveDateTime = row.try_get("created_at")?;
         let updated_naive: NaiveDateTime = row.try_get("updated_at")?;
-        let created_at = offset
-            .from_local_datetime(&created_naive)
-            .single()
-            .unwrap_or_else(|| {
-                let utc_dt = chrono::DateTime::<Utc>::from_naive_utc_and_offset(created_naive, Utc);
-                utc_dt.with_timezone(&offset)
-            });
-        let updated_at = offset
-            .from_local_datetime(&updated_naive)
-            .single()
-            .unwrap_or_else(|| {
-                let utc_dt = chrono::DateTime::<Utc>::from_naive_utc_and_offset(updated_naive, Utc);
-                utc_dt.with_timezone(&offset)
-            });
         Ok(Note {
             id: row.try_get("id")?,
             user_id: row.try_get("user_id")?,
@@ -58,8 +67,8 @@ impl<'r> sqlx::FromRow<'r, MySqlRow> for Note {
             content: row.try_get("content")?,
             category: row.try_get("category")?,
             tags: row.try_get("tags")?,
-            created_at,
-            updated_at,
+            created_at: utc_datetime(created_naive),
+            updated_at: utc_datetime(updated_naive),
         })
     }
 }
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OidcLoginQuery {
+    /// Finish the login with an HttpOnly session cookie
+    #[serde(default)]
+    pub session_cookie: bool,
+}
+
+/// Query string the identity provider appends when redirecting back to us.
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OidcProvidersResponse {
+    pub providers: Vec<String>,
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{mysql::MySqlRow, Row};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+use webauthn_rs::prelude::{
+    PublicKeyCredential, RegisterPublicKeyCredential, RequestChallengeResponse,
+};
+
+use super::utc_datetime;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PasskeyInfo {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize")]
+    pub created_at: DateTime<Utc>,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize_opt")]
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct FinishPasskeyRegistrationRequest {
+    #[validate(length(max = 64, message = "must be at most 64 characters"))]
+    pub name: Option<String>,
+    /// Result of `navigator.credentials.create()`
+    #[schema(value_type = Object)]
+    pub credential: RegisterPublicKeyCredential,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct StartPasskeyLoginRequest {
+    /// Username or email, as for password login
+    #[serde(alias = "email", alias = "username")]
+    #[validate(length(min = 1, max = 128, message = "must be 1-128 characters"))]
+    pub identifier: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StartPasskeyLoginResponse {
+    pub challenge_id: String,
+    /// Pass to `navigator.credentials.get()`
+    #[schema(value_type = Object)]
+    pub options: RequestChallengeResponse,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct FinishPasskeyLoginRequest {
+    #[validate(length(min = 1, max = 128, message = "must be 1-128 characters"))]
+    pub challenge_id: String,
+    /// Result of `navigator.credentials.get()`
+    #[schema(value_type = Object)]
+    pub credential: PublicKeyCredential,
+    #[serde(default)]
+    pub session_cookie: bool,
+}
+
+impl<'r> sqlx::FromRow<'r, MySqlRow> for PasskeyInfo {
+    fn from_row(row: &'r MySqlRow) -> Result<Self, sqlx::Error> {
+        let created_naive: NaiveDateTime = row.try_get("created_at")?;
+        let last_used_naive: Option<NaiveDateTime> = row.try_get("last_used_at")?;
+        Ok(PasskeyInfo {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+            created_at: utc_datetime(created_naive),
+            last_used_at: last_used_naive.map(utc_datetime),
+        })
+    }
+}
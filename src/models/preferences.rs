@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use super::validation::valid_time_zone;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Preferences {
+    /// IANA time zone name; `None` means the server default
+    pub time_zone: Option<String>,
+    /// Zone timestamps are rendered in when no preference or override is set
+    pub default_time_zone: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct UpdatePreferencesRequest {
+    /// `null` clears the preference
+    #[validate(custom(function = "valid_time_zone"))]
+    pub time_zone: Option<String>,
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use super::utc_from_timestamp;
+
+/// A signed-in device, identified by the `jti` of its session token.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SessionInfo {
+    pub id: String,
+    pub device_name: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize_opt")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize_opt")]
+    pub last_seen_at: Option<DateTime<Utc>>,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize_opt")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The session making this request
+    pub current: bool,
+}
+
+impl SessionInfo {
+    /// Builds the view from the Redis hash written at login.
+    pub fn from_fields(id: String, fields: &HashMap<String, String>, current: bool) -> Self {
+        let text = |k: &str| fields.get(k).filter(|v| !v.is_empty()).cloned();
+        let time = |k: &str| {
+            fields
+                .get(k)
+                .and_then(|v| v.parse().ok())
+                .and_then(utc_from_timestamp)
+        };
+        SessionInfo {
+            id,
+            device_name: text("device_name"),
+            user_agent: text("user_agent"),
+            ip: text("ip"),
+            created_at: time("created_at"),
+            last_seen_at: time("last_seen_at"),
+            expires_at: time("expires_at"),
+            current,
+        }
+    }
+}
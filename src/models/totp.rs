@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TotpEnrollResponse {
+    /// Base32 secret for manual entry in an authenticator app
+    pub secret: String,
+    /// `otpauth://` URI, also used as the QR code payload
+    pub otpauth_url: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct TotpCodeRequest {
+    #[validate(length(min = 1, max = 32, message = "must be 1-32 characters"))]
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct TotpLoginRequest {
+    #[validate(length(min = 1, max = 128, message = "must be 1-128 characters"))]
+    pub challenge: String,
+    /// Either a 6-digit TOTP code or one of the recovery codes
+    #[validate(length(min = 1, max = 32, message = "must be 1-32 characters"))]
+    pub code: String,
+    #[serde(default)]
+    pub session_cookie: bool,
+}
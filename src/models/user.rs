@@ -1,31 +1,75 @@
-use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{mysql::MySqlRow, Row};
+use utoipa::ToSchema;
 use uuid::Uuid;
+use validator::Validate;
 
-#[derive(Debug, Clone, Serialize)]
+use super::{
+    utc_datetime,
+    validation::{no_at_sign, not_blank, valid_email, valid_time_zone},
+};
+
+/// Account role; stored in `users.role`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Admin,
+}
+
+impl Role {
+    /// Unknown values fall back to the least privileged role.
+    pub fn parse(s: &str) -> Role {
+        match s {
+            "admin" => Role::Admin,
+            _ => Role::User,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct User {
     pub id: Uuid,
     pub username: String,
     pub email: String,
+    #[serde(skip_serializing)]
     pub password_hash: String,
-    pub created_at: DateTime<FixedOffset>,
+    #[serde(serialize_with = "crate::utils::time_zone::serialize")]
+    pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
 pub struct RegisterRequest {
+    #[validate(
+        custom(function = "not_blank"),
+        length(max = 64, message = "must be at most 64 characters"),
+        custom(function = "no_at_sign")
+    )]
     pub username: String,
+    #[validate(custom(function = "valid_email"))]
     pub email: String,
+    /// Strength is checked by the password policy, which needs the other fields
     pub password: String,
+    /// IANA time zone for rendering timestamps, e.g. from `Intl.DateTimeFormat().resolvedOptions().timeZone`
+    #[validate(custom(function = "valid_time_zone"))]
+    pub time_zone: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
 pub struct LoginRequest {
-    pub email: String,
+    /// Username or email; `email` and `username` are accepted as aliases
+    #[serde(alias = "email", alias = "username")]
+    #[validate(length(min = 1, max = 128, message = "must be 1-128 characters"))]
+    pub identifier: String,
+    #[validate(length(min = 1, message = "must not be empty"))]
     pub password: String,
+    /// Deliver the session as an HttpOnly cookie instead of in the body
+    #[serde(default)]
+    pub session_cookie: bool,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct UserInfo {
     pub id: Uuid,
     pub username: String,
@@ -33,32 +77,58 @@ pub struct UserInfo {
     pub avatar: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct LoginResponse {
-    pub token: String,
+    /// Omitted when the session was delivered as a cookie
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Present for cookie sessions; send it back in the `X-CSRF-Token` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csrf_token: Option<String>,
+    /// Set when an administrator requires a new password; only `/auth/password` is usable until then
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub password_reset_required: bool,
     pub user_info: UserInfo,
 }
 
-// Manual FromRow implementation for custom DateTime handling
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1, message = "must not be empty"))]
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// Returned instead of a token when the account has TOTP enabled; the client
+/// finishes the login by posting the challenge and a code to `/auth/login/totp`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LoginChallenge {
+    pub mfa_required: bool,
+    pub challenge: String,
+    pub expires_in: u64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum LoginOutcome {
+    Authenticated(LoginResponse),
+    TotpRequired(LoginChallenge),
+}
+
+/// Emails are stored and compared trimmed and lowercased.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+// Manual FromRow implementation; `created_at` is stored in UTC
 impl<'r> sqlx::FromRow<'r, MySqlRow> for User {
     fn from_row(row: &'r MySqlRow) -> Result<Self, sqlx::Error> {
         let naive: NaiveDateTime = row.try_get("created_at")?;
-        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
-        let created_at = offset
-            .from_local_datetime(&naive)
-            .single()
-            .unwrap_or_else(|| {
-                // Fallback: interpret as UTC and convert to +08:00
-                let utc_dt =
-                    chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(naive, chrono::Utc);
-                utc_dt.with_timezone(&offset)
-            });
         Ok(User {
             id: row.try_get("id")?,
             username: row.try_get("username")?,
             email: row.try_get("email")?,
             password_hash: row.try_get("password_hash")?,
-            created_at,
+            created_at: utc_datetime(naive),
         })
     }
 }
@@ -0,0 +1,99 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+use validator::{ValidateEmail, ValidationError};
+
+/// One reason a request field was rejected, rendered in the `details` array
+/// of an error response.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    /// Stable machine-readable code, e.g. `too_short`
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, code: &str, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.to_string(),
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Rejects values that are empty once surrounding whitespace is removed.
+pub fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(ValidationError::new("blank").with_message("must not be blank".into()));
+    }
+    Ok(())
+}
+
+/// Usernames may not contain `@` so a login identifier is never ambiguous.
+pub fn no_at_sign(value: &str) -> Result<(), ValidationError> {
+    if value.contains('@') {
+        return Err(
+            ValidationError::new("invalid_character").with_message("must not contain '@'".into())
+        );
+    }
+    Ok(())
+}
+
+/// Email addresses are stored trimmed, so the checks apply to the trimmed value.
+pub fn valid_email(value: &str) -> Result<(), ValidationError> {
+    let email = value.trim();
+    if !email.validate_email() {
+        return Err(ValidationError::new("invalid_email")
+            .with_message("must be a valid email address".into()));
+    }
+    if email.chars().count() > 128 {
+        return Err(
+            ValidationError::new("length").with_message("must be at most 128 characters".into())
+        );
+    }
+    Ok(())
+}
+
+/// Byte limit of a MySQL `TEXT` column.
+pub fn fits_text_column(value: &str) -> Result<(), ValidationError> {
+    if value.len() > 65_535 {
+        return Err(
+            ValidationError::new("too_long").with_message("must be at most 65535 bytes".into())
+        );
+    }
+    Ok(())
+}
+
+/// IANA time zone names such as `Europe/Berlin`.
+pub fn valid_time_zone(value: &str) -> Result<(), ValidationError> {
+    if crate::utils::time_zone::parse(value).is_none() {
+        return Err(ValidationError::new("invalid_time_zone")
+            .with_message("must be an IANA time zone name".into()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_email_ignores_surrounding_whitespace() {
+        assert!(valid_email(" a@b.com ").is_ok());
+        assert!(valid_email("a@b.com").is_ok());
+    }
+
+    #[test]
+    fn valid_email_rejects_malformed_and_long_addresses() {
+        assert_eq!(valid_email("nope").unwrap_err().code, "invalid_email");
+        assert_eq!(valid_email("   ").unwrap_err().code, "invalid_email");
+        let long = format!(
+            "{}@{}.{}.com",
+            "a".repeat(60),
+            "b".repeat(60),
+            "c".repeat(60)
+        );
+        assert_eq!(valid_email(&long).unwrap_err().code, "length");
+    }
+}
@@ -0,0 +1,108 @@
+use crate::app_middleware::{
+    auth_middleware::CurrentUser, client_info::ClientInfo, validated_json::ValidatedJson,
+};
+use crate::{
+    models::access_token::{AccessTokenInfo, CreateAccessTokenRequest, CreatedAccessToken},
+    routes::docs::ErrorResponse,
+    services::access_token_service,
+    AppState,
+};
+use axum::{
+    extract::{Extension, Path, State},
+    response::IntoResponse,
+    routing::{delete, post},
+    Json, Router,
+};
+use tracing::{error, info};
+use uuid::Uuid;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/auth/tokens", post(create).get(list))
+        .route("/auth/tokens/:id", delete(revoke))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/tokens",
+    operation_id = "create_access_token",
+    tag = "account",
+    request_body = CreateAccessTokenRequest,
+    responses(
+        (status = 201, description = "Token created; the plaintext is only shown here", body = CreatedAccessToken),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn create(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<CreateAccessTokenRequest>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/tokens#create", user_id = %user_id, name = %req.name, scopes = ?req.scopes, "incoming create access token");
+    match access_token_service::create_token(&state, user_id, req, &client).await {
+        Ok(token) => (axum::http::StatusCode::CREATED, Json(token)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/tokens",
+    operation_id = "list_access_tokens",
+    tag = "account",
+    responses(
+        (status = 200, description = "Personal access tokens", body = Vec<AccessTokenInfo>),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn list(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/tokens#list", user_id = %user_id, "incoming list access tokens");
+    match access_token_service::list_tokens(&state, user_id).await {
+        Ok(tokens) => (axum::http::StatusCode::OK, Json(tokens)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/tokens/{id}",
+    operation_id = "revoke_access_token",
+    tag = "account",
+    params(("id" = Uuid, Path, description = "Token id")),
+    responses(
+        (status = 204, description = "Token revoked"),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn revoke(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    client: ClientInfo,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/tokens#revoke", user_id = %user_id, id = %id, "incoming revoke access token");
+    match access_token_service::revoke_token(&state, user_id, id, &client).await {
+        Ok(()) => (axum::http::StatusCode::NO_CONTENT, "").into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response<E: std::fmt::Display>(e: E) -> axum::response::Response {
+    error!(target = "http", error = %e, "access token route error");
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": e.to_string() })),
+    )
+        .into_response()
+}
@@ -0,0 +1,167 @@
+use crate::app_middleware::{auth_middleware::CurrentUser, client_info::ClientInfo};
+use crate::{
+    models::admin::{AdminUserInfo, AdminUserList, AdminUserQuery, UsageStats},
+    routes::docs::ErrorResponse,
+    services::admin_service,
+    AppState,
+};
+use axum::{
+    extract::{Extension, Path, Query, State},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use tracing::{error, info};
+use uuid::Uuid;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/admin/users", get(list_users))
+        .route("/admin/users/:id/disable", post(disable_user))
+        .route("/admin/users/:id/enable", post(enable_user))
+        .route(
+            "/admin/users/:id/force-password-reset",
+            post(force_password_reset),
+        )
+        .route("/admin/stats", get(stats))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users",
+    operation_id = "list_users",
+    tag = "admin",
+    params(AdminUserQuery),
+    responses(
+        (status = 200, description = "Page of users", body = AdminUserList),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 403, description = "Not an administrator"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn list_users(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: admin_id, .. }): Extension<CurrentUser>,
+    Query(query): Query<AdminUserQuery>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/admin/users", admin_id = %admin_id, q = ?query.q, "incoming admin list users");
+    match admin_service::list_users(&state, query).await {
+        Ok(users) => (axum::http::StatusCode::OK, Json(users)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/disable",
+    operation_id = "disable_user",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 200, description = "Account disabled and signed out everywhere", body = AdminUserInfo),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 403, description = "Not an administrator"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn disable_user(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: admin_id, .. }): Extension<CurrentUser>,
+    client: ClientInfo,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/admin/users/:id/disable", admin_id = %admin_id, id = %id, "incoming admin disable user");
+    match admin_service::disable_user(&state, admin_id, id, &client).await {
+        Ok(user) => (axum::http::StatusCode::OK, Json(user)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/enable",
+    operation_id = "enable_user",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 200, description = "Account enabled", body = AdminUserInfo),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 403, description = "Not an administrator"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn enable_user(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: admin_id, .. }): Extension<CurrentUser>,
+    client: ClientInfo,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/admin/users/:id/enable", admin_id = %admin_id, id = %id, "incoming admin enable user");
+    match admin_service::enable_user(&state, admin_id, id, &client).await {
+        Ok(user) => (axum::http::StatusCode::OK, Json(user)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/force-password-reset",
+    operation_id = "force_password_reset",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 200, description = "User must choose a new password at next sign-in", body = AdminUserInfo),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 403, description = "Not an administrator"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn force_password_reset(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: admin_id, .. }): Extension<CurrentUser>,
+    client: ClientInfo,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/admin/users/:id/force-password-reset", admin_id = %admin_id, id = %id, "incoming admin force password reset");
+    match admin_service::force_password_reset(&state, admin_id, id, &client).await {
+        Ok(user) => (axum::http::StatusCode::OK, Json(user)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/stats",
+    operation_id = "usage_stats",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Usage statistics", body = UsageStats),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 403, description = "Not an administrator"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn stats(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: admin_id, .. }): Extension<CurrentUser>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/admin/stats", admin_id = %admin_id, "incoming admin stats");
+    match admin_service::usage_stats(&state).await {
+        Ok(stats) => (axum::http::StatusCode::OK, Json(stats)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response<E: std::fmt::Display>(e: E) -> axum::response::Response {
+    error!(target = "http", error = %e, "admin route error");
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": e.to_string() })),
+    )
+        .into_response()
+}
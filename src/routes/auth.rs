@@ -1,15 +1,63 @@
-use axum::{extract::State, response::IntoResponse, routing::post, Json, Router};
+use crate::{
+    app_middleware::{
+        auth_middleware::{Authentication, CurrentUser},
+        client_info::ClientInfo,
+        validated_json::{ValidatedJson, ValidationRejection},
+    },
+    models::{
+        totp::TotpLoginRequest,
+        user::{
+            ChangePasswordRequest, LoginOutcome, LoginRequest, LoginResponse, RegisterRequest, User,
+        },
+    },
+    routes::docs::ErrorResponse,
+    services::auth_service,
+    utils::session_cookie::{clear_session_cookies, set_session_cookies},
+    AppState,
+};
+use axum::{
+    extract::{Extension, State},
+    response::IntoResponse,
+    routing::post,
+    Json, Router,
+};
 use serde_json::json;
-use crate::{models::user::{RegisterRequest, LoginRequest}, services::auth_service, AppState};
+use tower_cookies::Cookies;
 use tracing::{error, info};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/auth/register", post(register))
         .route("/auth/login", post(login))
+        .route("/auth/login/totp", post(login_totp))
 }
 
-async fn register(State(state): State<AppState>, Json(req): Json<RegisterRequest>) -> impl IntoResponse {
+/// Routes that need a signed-in session.
+pub fn session_router() -> Router<AppState> {
+    Router::new().route("/auth/logout", post(logout))
+}
+
+/// Password change; stays reachable while an administrator forces a reset.
+pub fn password_router() -> Router<AppState> {
+    Router::new().route("/auth/password", post(change_password))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/register",
+    operation_id = "register",
+    tag = "auth",
+    request_body = RegisterRequest,
+    responses(
+        (status = 201, description = "Account created", body = User),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+)]
+async fn register(
+    State(state): State<AppState>,
+    ValidatedJson(req): ValidatedJson<RegisterRequest>,
+) -> impl IntoResponse {
     info!(target = "http", route = "/auth/register", username = %req.username, email = %req.email, "incoming register request");
     match auth_service::register(&state, req).await {
         Ok(user) => (axum::http::StatusCode::CREATED, Json(user)).into_response(),
@@ -17,18 +65,166 @@ async fn register(State(state): State<AppState>, Json(req): Json<RegisterRequest
     }
 }
 
-async fn login(State(state): State<AppState>, Json(req): Json<LoginRequest>) -> impl IntoResponse {
-    info!(target = "http", route = "/auth/login", email = %req.email, "incoming login request");
-    match auth_service::login(&state, req).await {
-        Ok(resp) => (axum::http::StatusCode::OK, Json(resp)).into_response(),
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    operation_id = "login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Signed in, or a TOTP challenge when two-factor authentication is enabled", body = LoginOutcome),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+)]
+async fn login(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<LoginRequest>,
+) -> impl IntoResponse {
+    info!(
+        target = "http",
+        route = "/auth/login",
+        session_cookie = req.session_cookie,
+        "incoming login request"
+    );
+    let session_cookie = req.session_cookie;
+    match auth_service::login(&state, req, &client).await {
+        Ok(LoginOutcome::Authenticated(mut resp)) => {
+            if session_cookie {
+                set_session_cookies(
+                    &cookies,
+                    &state.config,
+                    &mut resp,
+                    state.config.token_ttl_minutes,
+                );
+            }
+            (axum::http::StatusCode::OK, Json(resp)).into_response()
+        }
+        Ok(challenge) => (axum::http::StatusCode::OK, Json(challenge)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/login/totp",
+    operation_id = "login_totp",
+    tag = "auth",
+    request_body = TotpLoginRequest,
+    responses(
+        (status = 200, description = "Signed in", body = LoginResponse),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+)]
+async fn login_totp(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<TotpLoginRequest>,
+) -> impl IntoResponse {
+    info!(
+        target = "http",
+        route = "/auth/login/totp",
+        "incoming TOTP login request"
+    );
+    let session_cookie = req.session_cookie;
+    match auth_service::login_with_totp(&state, req, &client).await {
+        Ok(mut resp) => {
+            if session_cookie {
+                set_session_cookies(
+                    &cookies,
+                    &state.config,
+                    &mut resp,
+                    state.config.token_ttl_minutes,
+                );
+            }
+            (axum::http::StatusCode::OK, Json(resp)).into_response()
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    operation_id = "logout",
+    tag = "auth",
+    responses(
+        (status = 204, description = "Session ended"),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn logout(
+    State(state): State<AppState>,
+    Extension(auth): Extension<Authentication>,
+    cookies: Cookies,
+    client: ClientInfo,
+) -> impl IntoResponse {
+    info!(
+        target = "http",
+        route = "/auth/logout",
+        "incoming logout request"
+    );
+    let claims = match auth.claims {
+        Some(c) => c,
+        None => return (axum::http::StatusCode::FORBIDDEN, "").into_response(),
+    };
+    match auth_service::logout(&state, &claims, &client).await {
+        Ok(()) => {
+            clear_session_cookies(&cookies, &state.config);
+            (axum::http::StatusCode::NO_CONTENT, "").into_response()
+        }
         Err(e) => error_response(e),
     }
 }
 
-fn error_response<E: std::fmt::Display>(e: E) -> axum::response::Response {
+#[utoipa::path(
+    post,
+    path = "/auth/password",
+    operation_id = "change_password",
+    tag = "auth",
+    request_body = ChangePasswordRequest,
+    responses(
+        (status = 204, description = "Password changed; other sessions are signed out"),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn change_password(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    Extension(auth): Extension<Authentication>,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<ChangePasswordRequest>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/password", user_id = %user_id, "incoming change password request");
+    let current_jti = auth.claims.as_ref().map(|c| c.jti.as_str()).unwrap_or("");
+    match auth_service::change_password(&state, user_id, current_jti, req, &client).await {
+        Ok(()) => (axum::http::StatusCode::NO_CONTENT, "").into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response(e: auth_service::AuthError) -> axum::response::Response {
     error!(target = "http", error = %e, "auth route error");
-    (
-        axum::http::StatusCode::BAD_REQUEST,
-        Json(json!({ "error": e.to_string() })),
-    ).into_response()
-}
\ No newline at end of file
+    match &e {
+        auth_service::AuthError::WeakPassword(details) => ValidationRejection::new(
+            axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+            "password does not meet the password policy",
+            details.clone(),
+        )
+        .into_response(),
+        _ => (
+            axum::http::StatusCode::BAD_REQUEST,
+            Json(json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
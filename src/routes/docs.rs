@@ -0,0 +1,132 @@
+//! OpenAPI document generated from the `#[utoipa::path]` annotations on the
+//! route handlers, served as JSON with Swagger UI and Redoc front ends.
+
+use serde::Serialize;
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi, ToSchema,
+};
+use utoipa_redoc::{Redoc, Servable};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::{models::validation::FieldError, AppState};
+
+pub const OPENAPI_PATH: &str = "/api/tiny-note/openapi.json";
+pub const SWAGGER_UI_PATH: &str = "/api/tiny-note/docs";
+pub const REDOC_PATH: &str = "/api/tiny-note/redoc";
+
+/// Body of every error response.
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+    /// Per-field reasons for validation failures (`422`)
+    pub details: Option<Vec<FieldError>>,
+}
+
+/// Result of signing out the other devices.
+#[derive(Serialize, ToSchema)]
+pub struct RevokedSessions {
+    pub revoked: usize,
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Tiny Note API",
+        description = "Notes, accounts and authentication. Timestamps are rendered in the caller's time zone, see `Accept-Timezone`."
+    ),
+    paths(super::jwks, super::metrics::metrics),
+    nest((path = "/api/tiny-note", api = TinyNoteApi)),
+    modifiers(&SecuritySchemes),
+)]
+pub struct ApiDoc;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::health_check,
+        super::health::live,
+        super::health::ready,
+        super::auth::register,
+        super::auth::login,
+        super::auth::login_totp,
+        super::auth::logout,
+        super::auth::change_password,
+        super::totp::enroll,
+        super::totp::confirm,
+        super::totp::regenerate_recovery_codes,
+        super::totp::disable,
+        super::passkeys::login_start,
+        super::passkeys::login_finish,
+        super::passkeys::list,
+        super::passkeys::remove,
+        super::passkeys::register_start,
+        super::passkeys::register_finish,
+        super::oidc::providers,
+        super::oidc::login,
+        super::oidc::callback,
+        super::sessions::list,
+        super::sessions::revoke,
+        super::sessions::revoke_others,
+        super::preferences::show,
+        super::preferences::update,
+        super::security_events::list,
+        super::access_tokens::create,
+        super::access_tokens::list,
+        super::access_tokens::revoke,
+        super::notes::create,
+        super::notes::list,
+        super::notes::get_one,
+        super::notes::update,
+        super::notes::remove,
+        super::admin::list_users,
+        super::admin::disable_user,
+        super::admin::enable_user,
+        super::admin::force_password_reset,
+        super::admin::stats,
+    ),
+    tags(
+        (name = "auth", description = "Registration, login and passwords"),
+        (name = "totp", description = "Two-factor authentication"),
+        (name = "passkeys", description = "WebAuthn passkeys"),
+        (name = "oidc", description = "Single sign-on"),
+        (name = "account", description = "Sessions, preferences, security events and personal access tokens"),
+        (name = "notes", description = "Notes; also usable with personal access tokens"),
+        (name = "admin", description = "Administration, admins only"),
+        (name = "system", description = "Health and key discovery"),
+    )
+)]
+struct TinyNoteApi;
+
+/// `bearer` takes a login JWT or a personal access token; `session_cookie`
+/// is the browser alternative and needs the `X-CSRF-Token` header on writes.
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "session_cookie",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(
+                crate::utils::session_cookie::SESSION_COOKIE,
+            ))),
+        );
+    }
+}
+
+/// `GET /api/tiny-note/openapi.json`, Swagger UI and Redoc.
+pub fn router() -> axum::Router<AppState> {
+    let doc = ApiDoc::openapi();
+    axum::Router::new()
+        .merge(SwaggerUi::new(SWAGGER_UI_PATH).url(OPENAPI_PATH, doc.clone()))
+        .merge(Redoc::with_url(REDOC_PATH, doc))
+}
@@ -0,0 +1,60 @@
+use crate::{
+    models::health::{CheckStatus, Liveness, Readiness},
+    services::health_service,
+    AppState,
+};
+use axum::{
+    extract::State,
+    http::{header::CACHE_CONTROL, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use tracing::warn;
+
+/// Probes for the orchestrator: liveness only says the process serves
+/// requests, readiness also requires MySQL and Redis.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/health/live", get(live))
+        .route("/health/ready", get(ready))
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    operation_id = "liveness",
+    tag = "system",
+    responses((status = 200, description = "The process is running", body = Liveness)),
+)]
+async fn live() -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [(CACHE_CONTROL, "no-store")],
+        Json(Liveness {
+            status: CheckStatus::Up,
+        }),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    operation_id = "readiness",
+    tag = "system",
+    responses(
+        (status = 200, description = "All dependencies are reachable", body = Readiness),
+        (status = 503, description = "At least one dependency is down", body = Readiness),
+    ),
+)]
+async fn ready(State(state): State<AppState>) -> impl IntoResponse {
+    let readiness = health_service::readiness(&state).await;
+    let status = match readiness.status {
+        CheckStatus::Up => StatusCode::OK,
+        CheckStatus::Down => {
+            warn!(target = "http", checks = ?readiness.checks, "readiness check failed");
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    };
+    (status, [(CACHE_CONTROL, "no-store")], Json(readiness))
+}
@@ -0,0 +1,72 @@
+use crate::{services::health_service, AppState};
+use axum::{
+    extract::State,
+    http::{
+        header::{AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/metrics", get(metrics))
+}
+
+/// Prometheus scrape endpoint. Requires `Authorization: Bearer <METRICS_TOKEN>`
+/// when a token is configured.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    operation_id = "metrics",
+    tag = "system",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or wrong metrics token"),
+    ),
+    security((), ("bearer" = [])),
+)]
+async fn metrics(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Some(expected) = &state.config.metrics_token {
+        let provided = headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or("");
+        // Comparing digests keeps the comparison independent of where the strings differ
+        if Sha256::digest(provided) != Sha256::digest(expected) {
+            warn!(
+                target = "http",
+                route = "/metrics",
+                "rejected scrape with a missing or wrong token"
+            );
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+    }
+
+    // Gauges sampled at scrape time
+    let pool = health_service::pool_stats(&state.db);
+    metrics::gauge!("db_pool_connections", "state" => "idle").set(pool.idle as f64);
+    metrics::gauge!("db_pool_connections", "state" => "in_use").set(pool.in_use as f64);
+    metrics::gauge!("db_pool_max_connections").set(pool.max as f64);
+    metrics::gauge!("redis_available").set(if state.revocations.is_redis_up() {
+        1.0
+    } else {
+        0.0
+    });
+
+    (
+        StatusCode::OK,
+        [
+            (CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE),
+            (CACHE_CONTROL, "no-store"),
+        ],
+        state.metrics.render(),
+    )
+        .into_response()
+}
@@ -1,18 +1,38 @@
-use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
-use axum::{
-    http::{Method, StatusCode},
-    response::IntoResponse,
-    Json, Router,
-};
+use axum::{http::StatusCode, response::IntoResponse, Json, Router};
 use serde_json::json;
-use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+use tower_cookies::CookieManagerLayer;
 use tower_http::services::ServeDir;
 
+use crate::app_middleware::auth_middleware::{
+    require_admin, require_auth, require_auth_allow_password_reset, require_note_scopes,
+    require_session,
+};
 use crate::AppState;
 
+pub mod access_tokens;
+pub mod admin;
 pub mod auth;
+pub mod docs;
+pub mod health;
+pub mod metrics;
 pub mod notes;
+pub mod oidc;
+pub mod passkeys;
+pub mod preferences;
+pub mod redirect;
+pub mod security_events;
+pub mod sessions;
+pub mod totp;
 
+/// Static liveness response kept for existing monitors; probes should use
+/// `/health/live` and `/health/ready`.
+#[utoipa::path(
+    get,
+    path = "/health",
+    operation_id = "health",
+    tag = "system",
+    responses((status = 200, description = "Service is up", body = Object)),
+)]
 async fn health_check() -> impl IntoResponse {
     (
         StatusCode::OK,
@@ -23,37 +43,92 @@ async fn health_check() -> impl IntoResponse {
     )
 }
 
+/// Public verification keys for services that validate our tokens.
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    operation_id = "jwks",
+    tag = "system",
+    responses((status = 200, description = "JWK Set with the current verification keys", body = Object)),
+)]
+async fn jwks(axum::extract::State(state): axum::extract::State<AppState>) -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [(axum::http::header::CACHE_CONTROL, "public, max-age=300")],
+        Json(state.jwt_keys.jwks()),
+    )
+}
+
 pub fn build_router(state: &AppState) -> Router {
     let auth_routes = auth::router();
-    let notes_routes = notes::router().route_layer(axum::middleware::from_fn_with_state(
-        state.clone(),
-        crate::app_middleware::auth_middleware::require_auth,
-    ));
-
-    // CORS：允许任意域名，并支持携带 Cookie（通过镜像请求的 Origin）
-    let cors = CorsLayer::new()
-        .allow_origin(AllowOrigin::mirror_request())
-        .allow_methods(AllowMethods::list([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::DELETE,
-            Method::OPTIONS,
-        ]))
-        .allow_headers(AllowHeaders::list([AUTHORIZATION, CONTENT_TYPE]))
-        .allow_credentials(true);
+    let notes_routes = notes::router()
+        .route_layer(axum::middleware::from_fn(require_note_scopes))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            require_auth,
+        ));
+    // Account management: interactive sessions only, not personal access tokens
+    let account_routes = Router::new()
+        .merge(auth::session_router())
+        .merge(totp::router())
+        .merge(passkeys::router())
+        .merge(sessions::router())
+        .merge(preferences::router())
+        .merge(security_events::router())
+        .merge(access_tokens::router())
+        .route_layer(axum::middleware::from_fn(require_session))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            require_auth,
+        ));
+    let password_routes = auth::password_router()
+        .route_layer(axum::middleware::from_fn(require_session))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            require_auth_allow_password_reset,
+        ));
+    let admin_routes = admin::router()
+        .route_layer(axum::middleware::from_fn(require_admin))
+        .route_layer(axum::middleware::from_fn(require_session))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            require_auth,
+        ));
 
     let api = Router::new()
         .route("/health", axum::routing::get(health_check))
+        .merge(health::router())
         .merge(auth_routes)
+        .merge(passkeys::public_router())
+        .merge(oidc::router())
         .merge(notes_routes)
-        .nest_service("/static", ServeDir::new("static"));
+        .merge(account_routes)
+        .merge(password_routes)
+        .merge(admin_routes)
+        .nest_service("/static", ServeDir::new("static"))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            crate::middleware::time_zone::scope_time_zone,
+        ));
 
     Router::new()
+        .route("/.well-known/jwks.json", axum::routing::get(jwks))
+        .merge(docs::router())
+        .merge(metrics::router())
         .nest("/api/tiny-note", api)
         .layer(axum::middleware::from_fn(
             crate::middleware::logging::request_logger,
         ))
-        .layer(cors)
+        .layer(axum::middleware::from_fn(
+            crate::middleware::metrics::track_requests,
+        ))
+        .layer(axum::middleware::from_fn(
+            crate::middleware::trace_context::trace_requests,
+        ))
+        .layer(axum::middleware::from_fn(
+            crate::middleware::request_id::set_request_id,
+        ))
+        .layer(CookieManagerLayer::new())
+        .layer(crate::middleware::cors::cors_layer(&state.config))
         .with_state(state.clone())
 }
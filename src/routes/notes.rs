@@ -1,6 +1,7 @@
-use crate::app_middleware::auth_middleware::CurrentUser;
+use crate::app_middleware::{auth_middleware::CurrentUser, validated_json::ValidatedJson};
 use crate::{
-    models::note::{CreateNoteRequest, UpdateNoteRequest},
+    models::note::{CreateNoteRequest, Note, UpdateNoteRequest},
+    routes::docs::ErrorResponse,
     services::note_service,
     AppState,
 };
@@ -22,10 +23,24 @@ pub fn router() -> Router<AppState> {
         .route("/notes/:id", get(get_one).put(update).delete(remove))
 }
 
+#[utoipa::path(
+    post,
+    path = "/notes",
+    operation_id = "create_note",
+    tag = "notes",
+    request_body = CreateNoteRequest,
+    responses(
+        (status = 201, description = "Note created", body = Note),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
 async fn create(
     State(state): State<AppState>,
-    Extension(CurrentUser(user_id)): Extension<CurrentUser>,
-    Json(req): Json<CreateNoteRequest>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    ValidatedJson(req): ValidatedJson<CreateNoteRequest>,
 ) -> impl IntoResponse {
     info!(target = "http", route = "/notes#create", user_id = %user_id, title = %req.title, tags = ?req.tags, "incoming create note");
     match note_service::create_note(&state, user_id, req).await {
@@ -34,9 +49,22 @@ async fn create(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/notes",
+    operation_id = "list_notes",
+    tag = "notes",
+    params(("tag" = Option<String>, Query, description = "Only notes with this tag"), ("q" = Option<String>, Query, description = "Search in title and content")),
+    responses(
+        (status = 200, description = "Notes of the current user", body = Vec<Note>),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
 async fn list(
     State(state): State<AppState>,
-    Extension(CurrentUser(user_id)): Extension<CurrentUser>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
     Query(params): Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let tag = params.get("tag").cloned();
@@ -48,9 +76,22 @@ async fn list(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/notes/{id}",
+    operation_id = "get_note",
+    tag = "notes",
+    params(("id" = Uuid, Path, description = "Note id")),
+    responses(
+        (status = 200, description = "The note", body = Note),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
 async fn get_one(
     State(state): State<AppState>,
-    Extension(CurrentUser(user_id)): Extension<CurrentUser>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
     Path(id): Path<Uuid>,
 ) -> impl IntoResponse {
     info!(target = "http", route = "/notes#get", user_id = %user_id, id = %id, "incoming get note");
@@ -60,11 +101,26 @@ async fn get_one(
     }
 }
 
+#[utoipa::path(
+    put,
+    path = "/notes/{id}",
+    operation_id = "update_note",
+    tag = "notes",
+    params(("id" = Uuid, Path, description = "Note id")),
+    request_body = UpdateNoteRequest,
+    responses(
+        (status = 200, description = "Updated note", body = Note),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
 async fn update(
     State(state): State<AppState>,
-    Extension(CurrentUser(user_id)): Extension<CurrentUser>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateNoteRequest>,
+    ValidatedJson(req): ValidatedJson<UpdateNoteRequest>,
 ) -> impl IntoResponse {
     info!(target = "http", route = "/notes#update", user_id = %user_id, id = %id, "incoming update note");
     match note_service::update_note(&state, user_id, id, req).await {
@@ -73,9 +129,22 @@ async fn update(
     }
 }
 
+#[utoipa::path(
+    delete,
+    path = "/notes/{id}",
+    operation_id = "delete_note",
+    tag = "notes",
+    params(("id" = Uuid, Path, description = "Note id")),
+    responses(
+        (status = 204, description = "Note deleted"),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
 async fn remove(
     State(state): State<AppState>,
-    Extension(CurrentUser(user_id)): Extension<CurrentUser>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
     Path(id): Path<Uuid>,
 ) -> impl IntoResponse {
     info!(target = "http", route = "/notes#delete", user_id = %user_id, id = %id, "incoming delete note");
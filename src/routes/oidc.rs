@@ -0,0 +1,123 @@
+use crate::{
+    app_middleware::client_info::ClientInfo,
+    models::{
+        oidc::{OidcCallbackQuery, OidcLoginQuery, OidcProvidersResponse},
+        user::LoginOutcome,
+    },
+    routes::docs::ErrorResponse,
+    services::oidc_service,
+    utils::session_cookie::{set_oidc_state_cookie, set_session_cookies, take_oidc_state_cookie},
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Redirect},
+    routing::get,
+    Json, Router,
+};
+use tower_cookies::Cookies;
+use tracing::{error, info};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/auth/oidc/providers", get(providers))
+        .route("/auth/oidc/:provider/login", get(login))
+        .route("/auth/oidc/:provider/callback", get(callback))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/providers",
+    operation_id = "list_oidc_providers",
+    tag = "oidc",
+    responses(
+        (status = 200, description = "Configured identity providers", body = OidcProvidersResponse),
+    ),
+)]
+async fn providers(State(state): State<AppState>) -> impl IntoResponse {
+    let providers = oidc_service::provider_names(&state);
+    (
+        axum::http::StatusCode::OK,
+        Json(OidcProvidersResponse { providers }),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/{provider}/login",
+    operation_id = "start_oidc_login",
+    tag = "oidc",
+    params(("provider" = String, Path, description = "Provider name"), OidcLoginQuery),
+    responses(
+        (status = 303, description = "Redirect to the identity provider"),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+    ),
+)]
+async fn login(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    cookies: Cookies,
+    Query(query): Query<OidcLoginQuery>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/oidc/:provider/login", provider = %provider, "incoming OIDC login");
+    match oidc_service::authorization_url(&state, &provider, query.session_cookie).await {
+        Ok((url, csrf_state)) => {
+            set_oidc_state_cookie(
+                &cookies,
+                &state.config,
+                &csrf_state,
+                oidc_service::AUTH_REQUEST_TTL_SECONDS,
+            );
+            Redirect::to(&url).into_response()
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/{provider}/callback",
+    operation_id = "finish_oidc_login",
+    tag = "oidc",
+    params(("provider" = String, Path, description = "Provider name"), OidcCallbackQuery),
+    responses(
+        (status = 200, description = "Signed in, or a TOTP challenge to finish at `/auth/login/totp` when two-factor authentication is enabled", body = LoginOutcome),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+    ),
+)]
+async fn callback(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    cookies: Cookies,
+    client: ClientInfo,
+    Query(query): Query<OidcCallbackQuery>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/oidc/:provider/callback", provider = %provider, "incoming OIDC callback");
+    let browser_state = take_oidc_state_cookie(&cookies, &state.config);
+    match oidc_service::handle_callback(&state, &provider, query, browser_state.as_deref(), &client)
+        .await
+    {
+        Ok((LoginOutcome::Authenticated(mut resp), session_cookie)) => {
+            if session_cookie {
+                set_session_cookies(
+                    &cookies,
+                    &state.config,
+                    &mut resp,
+                    state.config.token_ttl_minutes,
+                );
+            }
+            (axum::http::StatusCode::OK, Json(resp)).into_response()
+        }
+        Ok((challenge, _)) => (axum::http::StatusCode::OK, Json(challenge)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response<E: std::fmt::Display>(e: E) -> axum::response::Response {
+    error!(target = "http", error = %e, "oidc route error");
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": e.to_string() })),
+    )
+        .into_response()
+}
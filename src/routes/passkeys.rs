@@ -0,0 +1,212 @@
+use crate::app_middleware::{
+    auth_middleware::CurrentUser, client_info::ClientInfo, validated_json::ValidatedJson,
+};
+use crate::{
+    models::{
+        passkey::{
+            FinishPasskeyLoginRequest, FinishPasskeyRegistrationRequest, PasskeyInfo,
+            StartPasskeyLoginRequest, StartPasskeyLoginResponse,
+        },
+        user::LoginResponse,
+    },
+    routes::docs::ErrorResponse,
+    services::passkey_service,
+    utils::session_cookie::set_session_cookies,
+    AppState,
+};
+use axum::{
+    extract::{Extension, Path, State},
+    response::IntoResponse,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use tower_cookies::Cookies;
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Passkey sign-in ceremony, available without a token.
+pub fn public_router() -> Router<AppState> {
+    Router::new()
+        .route("/auth/passkeys/login/start", post(login_start))
+        .route("/auth/passkeys/login/finish", post(login_finish))
+}
+
+/// Passkey management for the signed-in user.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/auth/passkeys", get(list))
+        .route("/auth/passkeys/:id", delete(remove))
+        .route("/auth/passkeys/register/start", post(register_start))
+        .route("/auth/passkeys/register/finish", post(register_finish))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/passkeys/login/start",
+    operation_id = "start_passkey_login",
+    tag = "passkeys",
+    request_body = StartPasskeyLoginRequest,
+    responses(
+        (status = 200, description = "Challenge for `navigator.credentials.get()`", body = StartPasskeyLoginResponse),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+)]
+async fn login_start(
+    State(state): State<AppState>,
+    ValidatedJson(req): ValidatedJson<StartPasskeyLoginRequest>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/passkeys/login/start", identifier = %req.identifier, "incoming passkey login start");
+    match passkey_service::start_login(&state, req).await {
+        Ok(resp) => (axum::http::StatusCode::OK, Json(resp)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/passkeys/login/finish",
+    operation_id = "finish_passkey_login",
+    tag = "passkeys",
+    request_body = FinishPasskeyLoginRequest,
+    responses(
+        (status = 200, description = "Signed in", body = LoginResponse),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+)]
+async fn login_finish(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<FinishPasskeyLoginRequest>,
+) -> impl IntoResponse {
+    info!(
+        target = "http",
+        route = "/auth/passkeys/login/finish",
+        "incoming passkey login finish"
+    );
+    let session_cookie = req.session_cookie;
+    match passkey_service::finish_login(&state, req, &client).await {
+        Ok(mut resp) => {
+            if session_cookie {
+                set_session_cookies(
+                    &cookies,
+                    &state.config,
+                    &mut resp,
+                    state.config.token_ttl_minutes,
+                );
+            }
+            (axum::http::StatusCode::OK, Json(resp)).into_response()
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/passkeys",
+    operation_id = "list_passkeys",
+    tag = "passkeys",
+    responses(
+        (status = 200, description = "Registered passkeys", body = Vec<PasskeyInfo>),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn list(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/passkeys#list", user_id = %user_id, "incoming list passkeys");
+    match passkey_service::list_passkeys(&state, user_id).await {
+        Ok(passkeys) => (axum::http::StatusCode::OK, Json(passkeys)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/passkeys/{id}",
+    operation_id = "delete_passkey",
+    tag = "passkeys",
+    params(("id" = Uuid, Path, description = "Passkey id")),
+    responses(
+        (status = 204, description = "Passkey removed"),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn remove(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    client: ClientInfo,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/passkeys#delete", user_id = %user_id, id = %id, "incoming delete passkey");
+    match passkey_service::delete_passkey(&state, user_id, id, &client).await {
+        Ok(()) => (axum::http::StatusCode::NO_CONTENT, "").into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/passkeys/register/start",
+    operation_id = "start_passkey_registration",
+    tag = "passkeys",
+    responses(
+        (status = 200, description = "Options for `navigator.credentials.create()`", body = Object),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn register_start(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/passkeys/register/start", user_id = %user_id, "incoming passkey registration start");
+    match passkey_service::start_registration(&state, user_id).await {
+        Ok(resp) => (axum::http::StatusCode::OK, Json(resp)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/passkeys/register/finish",
+    operation_id = "finish_passkey_registration",
+    tag = "passkeys",
+    request_body = FinishPasskeyRegistrationRequest,
+    responses(
+        (status = 201, description = "Passkey registered", body = PasskeyInfo),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn register_finish(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<FinishPasskeyRegistrationRequest>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/passkeys/register/finish", user_id = %user_id, name = ?req.name, "incoming passkey registration finish");
+    match passkey_service::finish_registration(&state, user_id, req, &client).await {
+        Ok(passkey) => (axum::http::StatusCode::CREATED, Json(passkey)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response<E: std::fmt::Display>(e: E) -> axum::response::Response {
+    error!(target = "http", error = %e, "passkey route error");
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": e.to_string() })),
+    )
+        .into_response()
+}
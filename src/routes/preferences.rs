@@ -0,0 +1,76 @@
+use crate::app_middleware::{auth_middleware::CurrentUser, validated_json::ValidatedJson};
+use crate::{
+    models::preferences::{Preferences, UpdatePreferencesRequest},
+    routes::docs::ErrorResponse,
+    services::preference_service,
+    AppState,
+};
+use axum::{
+    extract::{Extension, State},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use tracing::{error, info};
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/auth/preferences", get(show).put(update))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/preferences",
+    operation_id = "get_preferences",
+    tag = "account",
+    responses(
+        (status = 200, description = "Current preferences", body = Preferences),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn show(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/preferences#show", user_id = %user_id, "incoming get preferences");
+    match preference_service::get_preferences(&state, user_id).await {
+        Ok(prefs) => (axum::http::StatusCode::OK, Json(prefs)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/auth/preferences",
+    operation_id = "update_preferences",
+    tag = "account",
+    request_body = UpdatePreferencesRequest,
+    responses(
+        (status = 200, description = "Updated preferences", body = Preferences),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn update(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    ValidatedJson(req): ValidatedJson<UpdatePreferencesRequest>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/preferences#update", user_id = %user_id, "incoming update preferences");
+    match preference_service::update_preferences(&state, user_id, req).await {
+        Ok(prefs) => (axum::http::StatusCode::OK, Json(prefs)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response<E: std::fmt::Display>(e: E) -> axum::response::Response {
+    error!(target = "http", error = %e, "preferences route error");
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": e.to_string() })),
+    )
+        .into_response()
+}
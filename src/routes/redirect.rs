@@ -0,0 +1,109 @@
+use axum::{
+    http::{header::HOST, uri::Authority, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    Router,
+};
+
+/// Router for the plain HTTP listener: every request is sent to the same
+/// host and path on the HTTPS port. 308 keeps the method and body.
+pub fn router(https_port: u16) -> Router {
+    Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        redirect(https_port, &headers, &uri)
+    })
+}
+
+fn redirect(https_port: u16, headers: &HeaderMap, uri: &Uri) -> Response {
+    let Some(host) = headers
+        .get(HOST)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.parse::<Authority>().ok())
+        // A Host header has no userinfo; `Authority` would accept and drop it
+        .filter(|a| !a.host().is_empty() && !a.as_str().contains('@'))
+    else {
+        return (StatusCode::BAD_REQUEST, "missing or invalid Host header").into_response();
+    };
+    let port = if https_port == 443 {
+        String::new()
+    } else {
+        format!(":{}", https_port)
+    };
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+    Redirect::permanent(&format!("https://{}{}{}", host.host(), port, path)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::header::LOCATION;
+
+    fn location(https_port: u16, host: Option<&str>, uri: &str) -> (StatusCode, Option<String>) {
+        let mut headers = HeaderMap::new();
+        if let Some(host) = host {
+            headers.insert(HOST, host.parse().unwrap());
+        }
+        let res = redirect(https_port, &headers, &uri.parse().unwrap());
+        let location = res
+            .headers()
+            .get(LOCATION)
+            .map(|l| l.to_str().unwrap().to_string());
+        (res.status(), location)
+    }
+
+    #[test]
+    fn default_port_is_left_out() {
+        assert_eq!(
+            location(443, Some("notes.example.com"), "/"),
+            (
+                StatusCode::PERMANENT_REDIRECT,
+                Some("https://notes.example.com/".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn http_port_in_host_is_replaced_by_the_https_port() {
+        assert_eq!(
+            location(8443, Some("notes.example.com:8080"), "/")
+                .1
+                .as_deref(),
+            Some("https://notes.example.com:8443/")
+        );
+        assert_eq!(
+            location(443, Some("notes.example.com:80"), "/")
+                .1
+                .as_deref(),
+            Some("https://notes.example.com/")
+        );
+        assert_eq!(
+            location(8443, Some("[::1]:8080"), "/").1.as_deref(),
+            Some("https://[::1]:8443/")
+        );
+    }
+
+    #[test]
+    fn path_and_query_are_preserved() {
+        assert_eq!(
+            location(
+                443,
+                Some("notes.example.com"),
+                "/api/tiny-note/notes?category=work&page=2"
+            )
+            .1
+            .as_deref(),
+            Some("https://notes.example.com/api/tiny-note/notes?category=work&page=2")
+        );
+    }
+
+    #[test]
+    fn missing_or_invalid_host_is_rejected() {
+        assert_eq!(location(443, None, "/"), (StatusCode::BAD_REQUEST, None));
+        assert_eq!(
+            location(443, Some("evil.com/path"), "/"),
+            (StatusCode::BAD_REQUEST, None)
+        );
+        assert_eq!(
+            location(443, Some("user@evil.com"), "/"),
+            (StatusCode::BAD_REQUEST, None)
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use crate::app_middleware::auth_middleware::CurrentUser;
+use crate::{
+    models::audit::{AuditEvent, AuditEventQuery},
+    routes::docs::ErrorResponse,
+    services::audit_service,
+    AppState,
+};
+use axum::{
+    extract::{Extension, Query, State},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use tracing::{error, info};
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/auth/security-events", get(list))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/security-events",
+    operation_id = "list_security_events",
+    tag = "account",
+    params(AuditEventQuery),
+    responses(
+        (status = 200, description = "Most recent events first", body = Vec<AuditEvent>),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn list(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    Query(query): Query<AuditEventQuery>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/security-events", user_id = %user_id, "incoming list security events");
+    match audit_service::list_events(&state, user_id, query).await {
+        Ok(events) => (axum::http::StatusCode::OK, Json(events)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response<E: std::fmt::Display>(e: E) -> axum::response::Response {
+    error!(target = "http", error = %e, "security events route error");
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": e.to_string() })),
+    )
+        .into_response()
+}
@@ -0,0 +1,118 @@
+use crate::app_middleware::{
+    auth_middleware::{Authentication, CurrentUser},
+    client_info::ClientInfo,
+};
+use crate::{
+    models::session::SessionInfo,
+    routes::docs::{ErrorResponse, RevokedSessions},
+    services::session_service,
+    AppState,
+};
+use axum::{
+    extract::{Extension, Path, State},
+    response::IntoResponse,
+    routing::{delete, get},
+    Json, Router,
+};
+use tracing::{error, info};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/auth/sessions", get(list).delete(revoke_others))
+        .route("/auth/sessions/:id", delete(revoke))
+}
+
+/// `jti` of the session making the request; these routes are session-only.
+fn current_jti(auth: &Authentication) -> &str {
+    auth.claims.as_ref().map(|c| c.jti.as_str()).unwrap_or("")
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/sessions",
+    operation_id = "list_sessions",
+    tag = "account",
+    responses(
+        (status = 200, description = "Signed-in devices", body = Vec<SessionInfo>),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn list(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    Extension(auth): Extension<Authentication>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/sessions#list", user_id = %user_id, "incoming list sessions");
+    match session_service::list_sessions(&state, user_id, current_jti(&auth)).await {
+        Ok(sessions) => (axum::http::StatusCode::OK, Json(sessions)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/sessions/{id}",
+    operation_id = "revoke_session",
+    tag = "account",
+    params(("id" = String, Path, description = "Session id")),
+    responses(
+        (status = 204, description = "Device signed out"),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn revoke(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    client: ClientInfo,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/sessions#revoke", user_id = %user_id, id = %id, "incoming revoke session");
+    match session_service::revoke_session(&state, user_id, &id, &client).await {
+        Ok(()) => (axum::http::StatusCode::NO_CONTENT, "").into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/sessions",
+    operation_id = "revoke_other_sessions",
+    tag = "account",
+    responses(
+        (status = 200, description = "All other devices signed out", body = RevokedSessions),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn revoke_others(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    Extension(auth): Extension<Authentication>,
+    client: ClientInfo,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/sessions#revoke_others", user_id = %user_id, "incoming revoke other sessions");
+    match session_service::sign_out_other_devices(&state, user_id, current_jti(&auth), &client)
+        .await
+    {
+        Ok(revoked) => (
+            axum::http::StatusCode::OK,
+            Json(serde_json::json!({ "revoked": revoked })),
+        )
+            .into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response<E: std::fmt::Display>(e: E) -> axum::response::Response {
+    error!(target = "http", error = %e, "session route error");
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": e.to_string() })),
+    )
+        .into_response()
+}
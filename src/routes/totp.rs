@@ -0,0 +1,137 @@
+use crate::app_middleware::{
+    auth_middleware::CurrentUser, client_info::ClientInfo, validated_json::ValidatedJson,
+};
+use crate::{
+    models::totp::{RecoveryCodesResponse, TotpCodeRequest, TotpEnrollResponse},
+    routes::docs::ErrorResponse,
+    services::totp_service,
+    AppState,
+};
+use axum::{
+    extract::{Extension, State},
+    response::IntoResponse,
+    routing::post,
+    Json, Router,
+};
+use tracing::{error, info};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/auth/totp/enroll", post(enroll))
+        .route("/auth/totp/confirm", post(confirm))
+        .route("/auth/totp/recovery-codes", post(regenerate_recovery_codes))
+        .route("/auth/totp/disable", post(disable))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/totp/enroll",
+    operation_id = "enroll_totp",
+    tag = "totp",
+    responses(
+        (status = 200, description = "Secret to add to an authenticator app", body = TotpEnrollResponse),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn enroll(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/totp/enroll", user_id = %user_id, "incoming TOTP enroll");
+    match totp_service::enroll(&state, user_id).await {
+        Ok(resp) => (axum::http::StatusCode::OK, Json(resp)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/totp/confirm",
+    operation_id = "confirm_totp",
+    tag = "totp",
+    request_body = TotpCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor authentication enabled", body = RecoveryCodesResponse),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn confirm(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<TotpCodeRequest>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/totp/confirm", user_id = %user_id, "incoming TOTP confirm");
+    match totp_service::confirm(&state, user_id, req, &client).await {
+        Ok(resp) => (axum::http::StatusCode::OK, Json(resp)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/totp/recovery-codes",
+    operation_id = "regenerate_recovery_codes",
+    tag = "totp",
+    request_body = TotpCodeRequest,
+    responses(
+        (status = 200, description = "New recovery codes; the old ones stop working", body = RecoveryCodesResponse),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<TotpCodeRequest>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/totp/recovery-codes", user_id = %user_id, "incoming TOTP recovery codes regeneration");
+    match totp_service::regenerate_recovery_codes(&state, user_id, req, &client).await {
+        Ok(resp) => (axum::http::StatusCode::OK, Json(resp)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/totp/disable",
+    operation_id = "disable_totp",
+    tag = "totp",
+    request_body = TotpCodeRequest,
+    responses(
+        (status = 204, description = "Two-factor authentication disabled"),
+        (status = 400, description = "Request rejected", body = ErrorResponse),
+        (status = 401, description = "Not signed in"),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+    ),
+    security(("bearer" = []), ("session_cookie" = [])),
+)]
+async fn disable(
+    State(state): State<AppState>,
+    Extension(CurrentUser { id: user_id, .. }): Extension<CurrentUser>,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<TotpCodeRequest>,
+) -> impl IntoResponse {
+    info!(target = "http", route = "/auth/totp/disable", user_id = %user_id, "incoming TOTP disable");
+    match totp_service::disable(&state, user_id, req, &client).await {
+        Ok(()) => (axum::http::StatusCode::NO_CONTENT, "").into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response<E: std::fmt::Display>(e: E) -> axum::response::Response {
+    error!(target = "http", error = %e, "totp route error");
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": e.to_string() })),
+    )
+        .into_response()
+}
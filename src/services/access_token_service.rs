@@ -0,0 +1,200 @@
+use crate::{
+    app_middleware::client_info::ClientInfo,
+    models::{
+        access_token::{AccessTokenInfo, CreateAccessTokenRequest, CreatedAccessToken, Scope},
+        audit::AuditEventType,
+    },
+    services::audit_service,
+    AppState,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Personal access tokens are recognised by this prefix in the `Authorization` header.
+pub const TOKEN_PREFIX: &str = "tnp_";
+
+#[derive(Debug)]
+pub enum AccessTokenError {
+    NotFound,
+    InvalidName,
+    InvalidScope(String),
+    Db(sqlx::Error),
+}
+
+impl std::fmt::Display for AccessTokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessTokenError::NotFound => write!(f, "access token not found"),
+            AccessTokenError::InvalidName => write!(f, "token name must be 1-64 characters"),
+            AccessTokenError::InvalidScope(s) => write!(f, "unknown scope: {}", s),
+            AccessTokenError::Db(e) => write!(f, "db error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AccessTokenError {}
+
+impl From<sqlx::Error> for AccessTokenError {
+    fn from(e: sqlx::Error) -> Self {
+        AccessTokenError::Db(e)
+    }
+}
+
+/// A personal access token that passed authentication.
+pub struct AuthenticatedToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub scopes: Vec<Scope>,
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn generate_token() -> String {
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect();
+    format!("{}{}", TOKEN_PREFIX, secret)
+}
+
+fn parse_scopes(requested: Option<Vec<String>>) -> Result<Vec<Scope>, AccessTokenError> {
+    let requested = match requested {
+        Some(r) => r,
+        None => return Ok(Scope::ALL.to_vec()),
+    };
+    let mut scopes = Vec::new();
+    for s in requested {
+        let scope = Scope::parse(&s).ok_or(AccessTokenError::InvalidScope(s))?;
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    if scopes.is_empty() {
+        return Err(AccessTokenError::InvalidScope(String::new()));
+    }
+    Ok(scopes)
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn create_token(
+    state: &AppState,
+    user_id: Uuid,
+    req: CreateAccessTokenRequest,
+    client: &ClientInfo,
+) -> Result<CreatedAccessToken, AccessTokenError> {
+    let name = req.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > 64 {
+        return Err(AccessTokenError::InvalidName);
+    }
+    let scopes = parse_scopes(req.scopes)?;
+
+    let id = Uuid::new_v4();
+    let token = generate_token();
+    let scopes = scopes
+        .iter()
+        .map(Scope::as_str)
+        .collect::<Vec<_>>()
+        .join(",");
+    sqlx::query("INSERT INTO personal_access_tokens (id, user_id, name, token_hash, token_prefix, scopes, expires_at, created_at) VALUES (?, ?, ?, ?, ?, ?, IF(? IS NULL, NULL, DATE_ADD(UTC_TIMESTAMP(), INTERVAL ? DAY)), UTC_TIMESTAMP())")
+        .bind(id)
+        .bind(user_id)
+        .bind(&name)
+        .bind(hash_token(&token))
+        .bind(&token[..TOKEN_PREFIX.len() + 4])
+        .bind(&scopes)
+        .bind(req.expires_in_days)
+        .bind(req.expires_in_days)
+        .execute(&state.db)
+        .await?;
+
+    let info = sqlx::query_as::<_, AccessTokenInfo>("SELECT id, name, token_prefix, scopes, expires_at, last_used_at, created_at FROM personal_access_tokens WHERE id = ?")
+        .bind(id)
+        .fetch_one(&state.db)
+        .await?;
+    let details = json!({ "token_id": id, "name": info.name, "scopes": info.scopes });
+    audit_service::record(
+        state,
+        Some(user_id),
+        AuditEventType::AccessTokenCreate,
+        true,
+        client,
+        details,
+    )
+    .await;
+    tracing::info!(user_id = %user_id, token_id = %id, scopes = %scopes, "Personal access token created");
+    Ok(CreatedAccessToken { token, info })
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn list_tokens(
+    state: &AppState,
+    user_id: Uuid,
+) -> Result<Vec<AccessTokenInfo>, AccessTokenError> {
+    let tokens = sqlx::query_as::<_, AccessTokenInfo>("SELECT id, name, token_prefix, scopes, expires_at, last_used_at, created_at FROM personal_access_tokens WHERE user_id = ? ORDER BY created_at DESC")
+        .bind(user_id)
+        .fetch_all(&state.db)
+        .await?;
+    Ok(tokens)
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn revoke_token(
+    state: &AppState,
+    user_id: Uuid,
+    token_id: Uuid,
+    client: &ClientInfo,
+) -> Result<(), AccessTokenError> {
+    let res = sqlx::query("DELETE FROM personal_access_tokens WHERE id = ? AND user_id = ?")
+        .bind(token_id)
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+    if res.rows_affected() == 0 {
+        return Err(AccessTokenError::NotFound);
+    }
+    let details = json!({ "token_id": token_id });
+    audit_service::record(
+        state,
+        Some(user_id),
+        AuditEventType::AccessTokenRevoke,
+        true,
+        client,
+        details,
+    )
+    .await;
+    tracing::info!(user_id = %user_id, token_id = %token_id, "Personal access token revoked");
+    Ok(())
+}
+
+/// Looks up an unexpired token; returns `None` for unknown, revoked or expired tokens.
+#[tracing::instrument(skip_all)]
+pub async fn authenticate(
+    state: &AppState,
+    token: &str,
+) -> Result<Option<AuthenticatedToken>, sqlx::Error> {
+    let row: Option<(Uuid, Uuid, String)> = sqlx::query_as("SELECT id, user_id, scopes FROM personal_access_tokens WHERE token_hash = ? AND (expires_at IS NULL OR expires_at > UTC_TIMESTAMP())")
+        .bind(hash_token(token))
+        .fetch_optional(&state.db)
+        .await?;
+    let (id, user_id, scopes) = match row {
+        Some(r) => r,
+        None => return Ok(None),
+    };
+
+    // Only touch last_used_at once a minute to avoid a write per request
+    sqlx::query("UPDATE personal_access_tokens SET last_used_at = UTC_TIMESTAMP() WHERE id = ? AND (last_used_at IS NULL OR last_used_at < DATE_SUB(UTC_TIMESTAMP(), INTERVAL 1 MINUTE))")
+        .bind(id)
+        .execute(&state.db)
+        .await?;
+
+    Ok(Some(AuthenticatedToken {
+        id,
+        user_id,
+        scopes: scopes.split(',').filter_map(Scope::parse).collect(),
+    }))
+}
@@ -0,0 +1,242 @@
+use crate::{
+    app_middleware::client_info::ClientInfo,
+    models::{
+        admin::{AdminUserInfo, AdminUserList, AdminUserQuery, UsageStats},
+        audit::AuditEventType,
+    },
+    services::{
+        audit_service,
+        session_service::{self, SessionError},
+    },
+    AppState,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+const DEFAULT_PER_PAGE: u32 = 20;
+const MAX_PER_PAGE: u32 = 100;
+
+#[derive(Debug)]
+pub enum AdminError {
+    NotFound,
+    /// Administrators cannot disable or reset their own account
+    SelfModification,
+    Db(sqlx::Error),
+    Session(SessionError),
+}
+
+impl std::fmt::Display for AdminError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdminError::NotFound => write!(f, "user not found"),
+            AdminError::SelfModification => {
+                write!(f, "administrators cannot change their own account here")
+            }
+            AdminError::Db(e) => write!(f, "db error: {}", e),
+            AdminError::Session(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for AdminError {}
+
+impl From<sqlx::Error> for AdminError {
+    fn from(e: sqlx::Error) -> Self {
+        AdminError::Db(e)
+    }
+}
+
+impl From<SessionError> for AdminError {
+    fn from(e: SessionError) -> Self {
+        AdminError::Session(e)
+    }
+}
+
+const USER_COLUMNS: &str =
+    "id, username, email, role, disabled_at, password_reset_required, created_at";
+
+#[tracing::instrument(skip_all)]
+pub async fn list_users(
+    state: &AppState,
+    query: AdminUserQuery,
+) -> Result<AdminUserList, AdminError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    let pattern = query
+        .q
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(|q| {
+            format!(
+                "%{}%",
+                q.replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_")
+            )
+        });
+
+    let filter = if pattern.is_some() {
+        " WHERE username LIKE ? OR email LIKE ?"
+    } else {
+        ""
+    };
+    let count_sql = format!("SELECT COUNT(*) FROM users{}", filter);
+    let sql = format!(
+        "SELECT {} FROM users{} ORDER BY created_at DESC LIMIT ? OFFSET ?",
+        USER_COLUMNS, filter
+    );
+    let mut count = sqlx::query_as::<_, (i64,)>(&count_sql);
+    let mut rows = sqlx::query_as::<_, AdminUserInfo>(&sql);
+    if let Some(p) = &pattern {
+        count = count.bind(p.clone()).bind(p.clone());
+        rows = rows.bind(p.clone()).bind(p.clone());
+    }
+    let (total,) = count.fetch_one(&state.db).await?;
+    let users = rows
+        .bind(per_page)
+        .bind((page - 1) as u64 * per_page as u64)
+        .fetch_all(&state.db)
+        .await?;
+    Ok(AdminUserList {
+        users,
+        total,
+        page,
+        per_page,
+    })
+}
+
+async fn fetch_user(state: &AppState, user_id: Uuid) -> Result<AdminUserInfo, AdminError> {
+    let sql = format!("SELECT {} FROM users WHERE id = ?", USER_COLUMNS);
+    sqlx::query_as::<_, AdminUserInfo>(&sql)
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or(AdminError::NotFound)
+}
+
+/// Disables an account and signs it out everywhere; personal access tokens stop working too.
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn disable_user(
+    state: &AppState,
+    admin_id: Uuid,
+    user_id: Uuid,
+    client: &ClientInfo,
+) -> Result<AdminUserInfo, AdminError> {
+    if admin_id == user_id {
+        return Err(AdminError::SelfModification);
+    }
+    let res = sqlx::query(
+        "UPDATE users SET disabled_at = COALESCE(disabled_at, UTC_TIMESTAMP()) WHERE id = ?",
+    )
+    .bind(user_id)
+    .execute(&state.db)
+    .await?;
+    if res.rows_affected() == 0 {
+        // No change also means the row may already be disabled
+        fetch_user(state, user_id).await?;
+    }
+    let revoked = session_service::revoke_all_sessions(state, user_id).await?;
+    let details = json!({ "admin_id": admin_id, "sessions_revoked": revoked });
+    audit_service::record(
+        state,
+        Some(user_id),
+        AuditEventType::AccountDisable,
+        true,
+        client,
+        details,
+    )
+    .await;
+    tracing::info!(admin_id = %admin_id, user_id = %user_id, "User disabled");
+    fetch_user(state, user_id).await
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn enable_user(
+    state: &AppState,
+    admin_id: Uuid,
+    user_id: Uuid,
+    client: &ClientInfo,
+) -> Result<AdminUserInfo, AdminError> {
+    sqlx::query("UPDATE users SET disabled_at = NULL WHERE id = ?")
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+    let user = fetch_user(state, user_id).await?;
+    let details = json!({ "admin_id": admin_id });
+    audit_service::record(
+        state,
+        Some(user_id),
+        AuditEventType::AccountEnable,
+        true,
+        client,
+        details,
+    )
+    .await;
+    tracing::info!(admin_id = %admin_id, user_id = %user_id, "User enabled");
+    Ok(user)
+}
+
+/// Requires a new password on next use and signs the user out everywhere.
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn force_password_reset(
+    state: &AppState,
+    admin_id: Uuid,
+    user_id: Uuid,
+    client: &ClientInfo,
+) -> Result<AdminUserInfo, AdminError> {
+    if admin_id == user_id {
+        return Err(AdminError::SelfModification);
+    }
+    sqlx::query("UPDATE users SET password_reset_required = 1 WHERE id = ?")
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+    let user = fetch_user(state, user_id).await?;
+    let revoked = session_service::revoke_all_sessions(state, user_id).await?;
+    let details = json!({ "admin_id": admin_id, "sessions_revoked": revoked });
+    audit_service::record(
+        state,
+        Some(user_id),
+        AuditEventType::PasswordResetForce,
+        true,
+        client,
+        details,
+    )
+    .await;
+    tracing::info!(admin_id = %admin_id, user_id = %user_id, "Password reset forced");
+    Ok(user)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn usage_stats(state: &AppState) -> Result<UsageStats, AdminError> {
+    let (users_total, users_disabled, admins, users_created_last_7_days): (i64, i64, i64, i64) = sqlx::query_as(
+        "SELECT COUNT(*), \
+                CAST(COALESCE(SUM(disabled_at IS NOT NULL), 0) AS SIGNED), \
+                CAST(COALESCE(SUM(role = 'admin'), 0) AS SIGNED), \
+                CAST(COALESCE(SUM(created_at >= DATE_SUB(UTC_TIMESTAMP(), INTERVAL 7 DAY)), 0) AS SIGNED) \
+         FROM users")
+        .fetch_one(&state.db)
+        .await?;
+    let (notes_total, notes_updated_last_7_days): (i64, i64) = sqlx::query_as(
+        "SELECT COUNT(*), \
+                CAST(COALESCE(SUM(updated_at >= DATE_SUB(UTC_TIMESTAMP(), INTERVAL 7 DAY)), 0) AS SIGNED) \
+         FROM notes")
+        .fetch_one(&state.db)
+        .await?;
+    let (active_access_tokens,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM personal_access_tokens WHERE expires_at IS NULL OR expires_at > UTC_TIMESTAMP()")
+        .fetch_one(&state.db)
+        .await?;
+    Ok(UsageStats {
+        users_total,
+        users_disabled,
+        admins,
+        users_created_last_7_days,
+        notes_total,
+        notes_updated_last_7_days,
+        active_access_tokens,
+    })
+}
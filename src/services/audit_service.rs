@@ -0,0 +1,55 @@
+use crate::{
+    app_middleware::client_info::ClientInfo,
+    models::audit::{AuditEvent, AuditEventQuery, AuditEventType},
+    AppState,
+};
+use uuid::Uuid;
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+/// Persists a security event. Failures are logged but never fail the request
+/// that triggered the event.
+#[tracing::instrument(skip_all)]
+pub async fn record(
+    state: &AppState,
+    user_id: Option<Uuid>,
+    event_type: AuditEventType,
+    success: bool,
+    client: &ClientInfo,
+    details: serde_json::Value,
+) {
+    let details = if details.is_null() {
+        None
+    } else {
+        Some(details.to_string())
+    };
+    let res = sqlx::query("INSERT INTO audit_events (id, user_id, event_type, success, ip, user_agent, details, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, UTC_TIMESTAMP())")
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(event_type.as_str())
+        .bind(success)
+        .bind(&client.ip)
+        .bind(&client.user_agent)
+        .bind(&details)
+        .execute(&state.db)
+        .await;
+    if let Err(e) = res {
+        tracing::error!(error = %e, event_type = event_type.as_str(), user_id = ?user_id, "Failed to record audit event");
+    }
+}
+
+/// Most recent security events of a user, newest first.
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn list_events(
+    state: &AppState,
+    user_id: Uuid,
+    query: AuditEventQuery,
+) -> Result<Vec<AuditEvent>, sqlx::Error> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    sqlx::query_as::<_, AuditEvent>("SELECT id, event_type, success, ip, user_agent, details, created_at FROM audit_events WHERE user_id = ? ORDER BY created_at DESC LIMIT ?")
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(&state.db)
+        .await
+}
@@ -1,19 +1,50 @@
 use crate::{
-    models::user::{LoginRequest, LoginResponse, RegisterRequest, User, UserInfo},
+    app_middleware::client_info::ClientInfo,
+    db::redis::{
+        delete_login_challenge, get_login_challenge, record_challenge_attempt,
+        store_login_challenge, take_login_challenge, RedisError,
+    },
+    models::{
+        audit::{AuditEventType, LoginMethod},
+        totp::TotpLoginRequest,
+        user::{
+            normalize_email, ChangePasswordRequest, LoginChallenge, LoginOutcome, LoginRequest,
+            LoginResponse, RegisterRequest, Role, User, UserInfo,
+        },
+        validation::FieldError,
+    },
+    services::{
+        audit_service,
+        session_service::{self, SessionError},
+        totp_service::{self, TotpError},
+    },
     utils::{
-        jwt::generate_token,
-        password::{hash_password, verify_password},
+        jwt::{generate_token, Claims},
+        password_policy::check_password,
+        time_zone,
     },
     AppState,
 };
+use chrono_tz::Tz;
+use serde_json::json;
 use sqlx::{self};
 use uuid::Uuid;
 
+/// Lifetime of the challenge handed out between the password and TOTP steps.
+const LOGIN_CHALLENGE_TTL_SECONDS: u64 = 300;
+const LOGIN_CHALLENGE_MAX_ATTEMPTS: i64 = 5;
+
 #[derive(Debug)]
 pub enum AuthError {
     Conflict,
     InvalidCredentials,
+    ChallengeExpired,
+    InvalidTotp,
+    AccountDisabled,
+    IncorrectPassword,
+    WeakPassword(Vec<FieldError>),
     Db(sqlx::Error),
+    Redis(RedisError),
     Internal(anyhow::Error),
 }
 
@@ -22,7 +53,15 @@ impl std::fmt::Display for AuthError {
         match self {
             AuthError::Conflict => write!(f, "username or email already exists"),
             AuthError::InvalidCredentials => write!(f, "invalid username or password"),
+            AuthError::ChallengeExpired => {
+                write!(f, "login challenge expired, please sign in again")
+            }
+            AuthError::InvalidTotp => write!(f, "invalid two-factor code"),
+            AuthError::AccountDisabled => write!(f, "account is disabled"),
+            AuthError::IncorrectPassword => write!(f, "current password is incorrect"),
+            AuthError::WeakPassword(_) => write!(f, "password does not meet the password policy"),
             AuthError::Db(e) => write!(f, "db error: {}", e),
+            AuthError::Redis(e) => write!(f, "{}", e),
             AuthError::Internal(e) => write!(f, "internal error: {}", e),
         }
     }
@@ -36,33 +75,131 @@ impl From<sqlx::Error> for AuthError {
     }
 }
 
+impl From<RedisError> for AuthError {
+    fn from(e: RedisError) -> Self {
+        AuthError::Redis(e)
+    }
+}
+
+impl From<SessionError> for AuthError {
+    fn from(e: SessionError) -> Self {
+        match e {
+            SessionError::Redis(e) => AuthError::Redis(e),
+            other => AuthError::Internal(anyhow::anyhow!(other.to_string())),
+        }
+    }
+}
+
+impl From<TotpError> for AuthError {
+    fn from(e: TotpError) -> Self {
+        match e {
+            TotpError::InvalidCode => AuthError::InvalidTotp,
+            TotpError::Db(e) => AuthError::Db(e),
+            other => AuthError::Internal(anyhow::anyhow!(other.to_string())),
+        }
+    }
+}
+
 impl From<anyhow::Error> for AuthError {
     fn from(e: anyhow::Error) -> Self {
         AuthError::Internal(e)
     }
 }
 
+/// Per-request account state checked by `require_auth`.
+#[derive(Debug, Clone, Copy)]
+pub struct AccountStatus {
+    pub role: Role,
+    pub disabled: bool,
+    pub password_reset_required: bool,
+    /// Preferred zone for rendering timestamps; unknown stored names count as unset
+    pub time_zone: Option<Tz>,
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn account_status(
+    state: &AppState,
+    user_id: Uuid,
+) -> Result<Option<AccountStatus>, sqlx::Error> {
+    let row: Option<(String, bool, bool, Option<String>)> = sqlx::query_as("SELECT role, disabled_at IS NOT NULL, password_reset_required, time_zone FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await?;
+    Ok(row.map(
+        |(role, disabled, password_reset_required, time_zone)| AccountStatus {
+            role: Role::parse(&role),
+            disabled,
+            password_reset_required,
+            time_zone: time_zone.as_deref().and_then(time_zone::parse),
+        },
+    ))
+}
+
+/// Finds a user by email (identifiers containing `@`) or username.
+#[tracing::instrument(skip_all)]
+pub async fn find_user_by_identifier(
+    state: &AppState,
+    identifier: &str,
+) -> Result<Option<User>, sqlx::Error> {
+    let identifier = identifier.trim();
+    let (column, value) = if identifier.contains('@') {
+        ("email", normalize_email(identifier))
+    } else {
+        ("username", identifier.to_string())
+    };
+    let sql = format!(
+        "SELECT id, username, email, password_hash, CAST(created_at AS DATETIME) AS created_at FROM users WHERE {} = ? LIMIT 1",
+        column
+    );
+    sqlx::query_as::<_, User>(&sql)
+        .bind(value)
+        .fetch_optional(&state.db)
+        .await
+}
+
+#[tracing::instrument(skip_all)]
 pub async fn register(state: &AppState, req: RegisterRequest) -> Result<User, AuthError> {
+    let username = req.username.trim();
+    let email = normalize_email(&req.email);
+
     let exists: Option<(i64,)> =
         sqlx::query_as("SELECT 1 as count FROM users WHERE username = ? OR email = ? LIMIT 1")
-            .bind(&req.username)
-            .bind(&req.email)
+            .bind(username)
+            .bind(&email)
             .fetch_optional(&state.db)
             .await?;
     if exists.is_some() {
         return Err(AuthError::Conflict);
     }
+    let violations = check_password(
+        &state.config,
+        "password",
+        &req.password,
+        &[username, &email],
+    )
+    .await;
+    if !violations.is_empty() {
+        return Err(AuthError::WeakPassword(violations));
+    }
 
     let user_id = Uuid::new_v4();
-    let password_hash = hash_password(&req.password)?;
+    let password_hash = state.passwords.hash(&req.password)?;
+
+    let time_zone = req
+        .time_zone
+        .as_deref()
+        .and_then(time_zone::parse)
+        .map(|tz| tz.name());
 
-    sqlx::query("INSERT INTO users (id, username, email, password_hash, created_at) VALUES (?, ?, ?, ?, CONVERT_TZ(UTC_TIMESTAMP(), '+00:00', '+08:00'))")
+    sqlx::query("INSERT INTO users (id, username, email, password_hash, time_zone, created_at) VALUES (?, ?, ?, ?, ?, UTC_TIMESTAMP())")
         .bind(user_id)
-        .bind(&req.username)
-        .bind(&req.email)
+        .bind(username)
+        .bind(&email)
         .bind(&password_hash)
+        .bind(time_zone)
         .execute(&state.db)
         .await?;
+    metrics::counter!("users_registered_total").increment(1);
 
     let user = sqlx::query_as::<_, User>("SELECT id, username, email, password_hash, CAST(created_at AS DATETIME) AS created_at FROM users WHERE id = ?")
         .bind(user_id)
@@ -71,26 +208,204 @@ pub async fn register(state: &AppState, req: RegisterRequest) -> Result<User, Au
     Ok(user)
 }
 
-pub async fn login(state: &AppState, req: LoginRequest) -> Result<LoginResponse, AuthError> {
-    let user = sqlx::query_as::<_, User>(
-        "SELECT id, username, email, password_hash, CAST(created_at AS DATETIME) AS created_at FROM users WHERE email = ? LIMIT 1")
-        .bind(&req.email)
-        .fetch_optional(&state.db)
-        .await?;
-    let user = match user {
+#[tracing::instrument(skip_all)]
+pub async fn login(
+    state: &AppState,
+    req: LoginRequest,
+    client: &ClientInfo,
+) -> Result<LoginOutcome, AuthError> {
+    let user = match find_user_by_identifier(state, &req.identifier).await? {
         Some(u) => u,
-        None => return Err(AuthError::InvalidCredentials),
+        None => {
+            // The identifier is not kept: users sometimes type their password into it
+            record_login_failure(
+                state,
+                None,
+                client,
+                LoginMethod::Password,
+                "unknown_account",
+            )
+            .await;
+            return Err(AuthError::InvalidCredentials);
+        }
     };
 
-    let valid = verify_password(&req.password, &user.password_hash)?;
+    let valid = state.passwords.verify(&req.password, &user.password_hash)?;
     if !valid {
+        record_login_failure(
+            state,
+            Some(user.id),
+            client,
+            LoginMethod::Password,
+            "invalid_password",
+        )
+        .await;
         return Err(AuthError::InvalidCredentials);
     }
+    if state.passwords.needs_rehash(&user.password_hash) {
+        rehash_password(state, &user, &req.password).await?;
+    }
+    complete_login(state, &user, client, LoginMethod::Password).await
+}
 
-    let (token, _) = generate_token(user.id, &state.jwt_secret, 60)?; // 60 minutes
+/// Finishes a login whose first factor (password, external identity) was
+/// accepted: accounts with TOTP enabled get a challenge for `/auth/login/totp`,
+/// others a session token.
+#[tracing::instrument(skip_all, fields(user_id = %user.id))]
+pub async fn complete_login(
+    state: &AppState,
+    user: &User,
+    client: &ClientInfo,
+    method: LoginMethod,
+) -> Result<LoginOutcome, AuthError> {
+    // Checked before the TOTP step so disabled accounts never get a challenge
+    if matches!(account_status(state, user.id).await?, Some(s) if s.disabled) {
+        record_login_failure(state, Some(user.id), client, method, "account_disabled").await;
+        return Err(AuthError::AccountDisabled);
+    }
+
+    if totp_service::is_enabled(state, user.id).await? {
+        let challenge = Uuid::new_v4().simple().to_string();
+        store_login_challenge(
+            &state.redis,
+            &challenge,
+            user.id,
+            LOGIN_CHALLENGE_TTL_SECONDS,
+        )
+        .await?;
+        tracing::info!(user_id = %user.id, method = method.as_str(), "First factor accepted, TOTP code required");
+        return Ok(LoginOutcome::TotpRequired(LoginChallenge {
+            mfa_required: true,
+            challenge,
+            expires_in: LOGIN_CHALLENGE_TTL_SECONDS,
+        }));
+    }
+
+    Ok(LoginOutcome::Authenticated(
+        issue_login_response(state, user, client, method).await?,
+    ))
+}
+
+/// Upgrades a stored hash to the current argon2 parameters and pepper.
+/// Only replaces the hash that was verified, in case the password changed concurrently.
+async fn rehash_password(state: &AppState, user: &User, password: &str) -> Result<(), AuthError> {
+    let new_hash = state.passwords.hash(password)?;
+    let res = sqlx::query("UPDATE users SET password_hash = ? WHERE id = ? AND password_hash = ?")
+        .bind(&new_hash)
+        .bind(user.id)
+        .bind(&user.password_hash)
+        .execute(&state.db)
+        .await?;
+    if res.rows_affected() > 0 {
+        tracing::info!(user_id = %user.id, "Password hash upgraded to current parameters");
+    }
+    Ok(())
+}
+
+/// Second login step for accounts with TOTP enabled.
+#[tracing::instrument(skip_all)]
+pub async fn login_with_totp(
+    state: &AppState,
+    req: TotpLoginRequest,
+    client: &ClientInfo,
+) -> Result<LoginResponse, AuthError> {
+    let user_id = get_login_challenge(&state.redis, &req.challenge)
+        .await?
+        .ok_or(AuthError::ChallengeExpired)?;
+
+    if !totp_service::verify_code(state, user_id, &req.code).await? {
+        let attempts = record_challenge_attempt(
+            &state.redis,
+            &req.challenge,
+            LOGIN_CHALLENGE_TTL_SECONDS as i64,
+        )
+        .await?;
+        if attempts >= LOGIN_CHALLENGE_MAX_ATTEMPTS {
+            delete_login_challenge(&state.redis, &req.challenge).await?;
+            tracing::warn!(user_id = %user_id, "Too many invalid TOTP codes, challenge discarded");
+        }
+        record_login_failure(
+            state,
+            Some(user_id),
+            client,
+            LoginMethod::Totp,
+            "invalid_code",
+        )
+        .await;
+        return Err(AuthError::InvalidTotp);
+    }
+    // Another request may have redeemed the challenge since it was read
+    if take_login_challenge(&state.redis, &req.challenge).await? != Some(user_id) {
+        return Err(AuthError::ChallengeExpired);
+    }
+
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, username, email, password_hash, CAST(created_at AS DATETIME) AS created_at FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&state.db)
+        .await?;
+    issue_login_response(state, &user, client, LoginMethod::Totp).await
+}
+
+/// Records a failed login attempt in the audit log and the `logins_failed_total` counter.
+#[tracing::instrument(skip_all)]
+pub async fn record_login_failure(
+    state: &AppState,
+    user_id: Option<Uuid>,
+    client: &ClientInfo,
+    method: LoginMethod,
+    reason: &str,
+) {
+    let details = json!({ "method": method.as_str(), "reason": reason });
+    audit_service::record(
+        state,
+        user_id,
+        AuditEventType::Login,
+        false,
+        client,
+        details,
+    )
+    .await;
+    count_failed_login(method, reason);
+}
+
+fn count_failed_login(method: LoginMethod, reason: &str) {
+    metrics::counter!("logins_failed_total", "method" => method.as_str(), "reason" => reason.to_string()).increment(1);
+}
+
+/// Issues a session token for a fully authenticated user and records the device it was issued to.
+#[tracing::instrument(skip_all)]
+pub async fn issue_login_response(
+    state: &AppState,
+    user: &User,
+    client: &ClientInfo,
+    method: LoginMethod,
+) -> Result<LoginResponse, AuthError> {
+    let status = account_status(state, user.id)
+        .await?
+        .ok_or(AuthError::InvalidCredentials)?;
+    if status.disabled {
+        record_login_failure(state, Some(user.id), client, method, "account_disabled").await;
+        return Err(AuthError::AccountDisabled);
+    }
+    let (token, claims) = generate_token(user.id, &state.jwt_keys, state.config.token_ttl_minutes)?;
+    session_service::record_session(state, &claims, client).await?;
+    let details = json!({ "method": method.as_str(), "session_id": claims.jti });
+    audit_service::record(
+        state,
+        Some(user.id),
+        AuditEventType::Login,
+        true,
+        client,
+        details,
+    )
+    .await;
+    metrics::counter!("logins_succeeded_total", "method" => method.as_str()).increment(1);
 
     let response = LoginResponse {
-        token: token.clone(),
+        token: Some(token),
+        csrf_token: None,
+        password_reset_required: status.password_reset_required,
         user_info: UserInfo {
             id: user.id,
             username: user.username.clone(),
@@ -108,7 +423,96 @@ pub async fn login(state: &AppState, req: LoginRequest) -> Result<LoginResponse,
         "User logged in successfully"
     );
 
-    // No need to store token in redis by default; blacklist on logout feature could be added.
-    // For demonstration, we simply return the token.
     Ok(response)
 }
+
+/// Sets a new password, clears a forced reset and signs out all other devices.
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn change_password(
+    state: &AppState,
+    user_id: Uuid,
+    current_jti: &str,
+    req: ChangePasswordRequest,
+    client: &ClientInfo,
+) -> Result<(), AuthError> {
+    let (username, email, password_hash): (String, String, String) =
+        sqlx::query_as("SELECT username, email, password_hash FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(&state.db)
+            .await?;
+    if !state
+        .passwords
+        .verify(&req.current_password, &password_hash)?
+    {
+        let details = json!({ "reason": "incorrect_password" });
+        audit_service::record(
+            state,
+            Some(user_id),
+            AuditEventType::PasswordChange,
+            false,
+            client,
+            details,
+        )
+        .await;
+        return Err(AuthError::IncorrectPassword);
+    }
+
+    let mut violations = check_password(
+        &state.config,
+        "new_password",
+        &req.new_password,
+        &[&username, &email],
+    )
+    .await;
+    if req.new_password == req.current_password {
+        violations.push(FieldError::new(
+            "new_password",
+            "unchanged",
+            "must differ from the current password",
+        ));
+    }
+    if !violations.is_empty() {
+        return Err(AuthError::WeakPassword(violations));
+    }
+    let new_hash = state.passwords.hash(&req.new_password)?;
+    sqlx::query("UPDATE users SET password_hash = ?, password_reset_required = 0 WHERE id = ?")
+        .bind(&new_hash)
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+    let revoked = session_service::revoke_other_sessions(state, user_id, current_jti).await?;
+    let details = json!({ "sessions_revoked": revoked });
+    audit_service::record(
+        state,
+        Some(user_id),
+        AuditEventType::PasswordChange,
+        true,
+        client,
+        details,
+    )
+    .await;
+    tracing::info!(user_id = %user_id, "Password changed");
+    Ok(())
+}
+
+/// Revokes the session token until it would have expired anyway.
+#[tracing::instrument(skip_all)]
+pub async fn logout(
+    state: &AppState,
+    claims: &Claims,
+    client: &ClientInfo,
+) -> Result<(), AuthError> {
+    session_service::end_session(state, claims).await?;
+    let details = json!({ "session_id": claims.jti });
+    audit_service::record(
+        state,
+        Some(claims.sub),
+        AuditEventType::Logout,
+        true,
+        client,
+        details,
+    )
+    .await;
+    tracing::info!(user_id = %claims.sub, jti = %claims.jti, "User logged out");
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::RedisFailurePolicy,
+    db,
+    models::health::{
+        CheckStatus, DependencyCheck, DependencyChecks, MySqlCheck, PoolStats, Readiness,
+    },
+    AppState,
+};
+
+/// Pings MySQL and Redis concurrently, each bounded by `HEALTH_CHECK_TIMEOUT_MS`.
+/// With `REDIS_FAILURE_POLICY=open` the service keeps working without Redis,
+/// so a Redis outage is reported but does not make the instance unready.
+#[tracing::instrument(skip_all)]
+pub async fn readiness(state: &AppState) -> Readiness {
+    let timeout = Duration::from_millis(state.config.health_check_timeout_ms);
+    let (mysql, redis) = tokio::join!(
+        check("mysql", timeout, db::mysql::ping(&state.db)),
+        check("redis", timeout, db::redis::ping(&state.redis)),
+    );
+    let redis_required = state.config.redis_failure_policy == RedisFailurePolicy::Closed;
+    let status = if mysql.status == CheckStatus::Up
+        && (redis.status == CheckStatus::Up || !redis_required)
+    {
+        CheckStatus::Up
+    } else {
+        CheckStatus::Down
+    };
+    Readiness {
+        status,
+        checks: DependencyChecks {
+            mysql: MySqlCheck {
+                check: mysql,
+                pool: pool_stats(&state.db),
+            },
+            redis,
+        },
+    }
+}
+
+/// The endpoint is unauthenticated, so the response only carries a generic
+/// reason; the driver's error (hosts, users, TLS details) goes to the log.
+async fn check<E: std::fmt::Display>(
+    dependency: &str,
+    timeout: Duration,
+    ping: impl Future<Output = Result<(), E>>,
+) -> DependencyCheck {
+    let started = Instant::now();
+    let error = match tokio::time::timeout(timeout, ping).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => {
+            tracing::warn!(dependency, error = %e, "readiness check failed");
+            Some("unavailable".to_string())
+        }
+        Err(_) => {
+            tracing::warn!(
+                dependency,
+                timeout_ms = timeout.as_millis() as u64,
+                "readiness check timed out"
+            );
+            Some("timed out".to_string())
+        }
+    };
+    DependencyCheck {
+        status: if error.is_none() {
+            CheckStatus::Up
+        } else {
+            CheckStatus::Down
+        },
+        latency_ms: started.elapsed().as_millis() as u64,
+        error,
+    }
+}
+
+pub fn pool_stats(pool: &sqlx::MySqlPool) -> PoolStats {
+    let size = pool.size();
+    let idle = pool.num_idle() as u32;
+    PoolStats {
+        size,
+        idle,
+        in_use: size.saturating_sub(idle),
+        max: pool.options().get_max_connections(),
+    }
+}
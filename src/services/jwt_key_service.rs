@@ -0,0 +1,268 @@
+use crate::{
+    config::Config,
+    db::mysql::{Traced, TracedPool},
+    utils::{
+        jwt::{algorithm_name, JwtKey, JwtKeys},
+        shutdown::Shutdown,
+    },
+    AppState,
+};
+use jsonwebtoken::Algorithm;
+use sqlx::MySqlConnection;
+use std::time::Duration;
+
+/// Builds the key holder for the configured algorithm; asymmetric keys are
+/// loaded afterwards by [`refresh_keys`].
+pub fn build_jwt_keys(cfg: &Config) -> JwtKeys {
+    match (cfg.jwt_algorithm, cfg.jwt_secret.as_ref()) {
+        (Algorithm::HS256, Some(secret)) => JwtKeys::hmac(secret.clone()),
+        _ => JwtKeys::asymmetric(),
+    }
+}
+
+/// MySQL named lock held while an instance checks for and creates a new key
+const ROTATION_LOCK: &str = "tiny_note_jwt_key_rotation";
+/// How long to wait for another instance that is rotating
+const ROTATION_LOCK_WAIT_SECONDS: i64 = 10;
+
+async fn newest_key_age(
+    conn: &mut MySqlConnection,
+    algorithm: Algorithm,
+) -> Result<Option<i64>, sqlx::Error> {
+    let newest: Option<(i64,)> = sqlx::query_as("SELECT TIMESTAMPDIFF(SECOND, created_at, UTC_TIMESTAMP()) FROM jwt_keys WHERE algorithm = ? ORDER BY created_at DESC LIMIT 1")
+        .bind(algorithm_name(algorithm))
+        .fetch_optional(Traced(&mut *conn))
+        .await?;
+    Ok(newest.map(|(age,)| age))
+}
+
+/// How long a key is kept after it was created. It signs for up to one
+/// rotation period, plus up to two refresh intervals until the rotation is
+/// noticed and its successor activated; its last tokens then live for the
+/// token TTL.
+fn key_retention_seconds(
+    rotation_seconds: i64,
+    token_ttl_seconds: i64,
+    refresh_seconds: i64,
+) -> i64 {
+    rotation_seconds + 2 * refresh_seconds + token_ttl_seconds
+}
+
+/// Creates a new key when the newest one is older than the rotation period.
+///
+/// Instances serialize on a named lock, so only one of them creates the
+/// key; the others see it when they get the lock.
+async fn rotate_if_due(
+    db: &TracedPool,
+    algorithm: Algorithm,
+    rotation_seconds: i64,
+    retention_seconds: i64,
+) -> anyhow::Result<()> {
+    let mut conn = db.acquire().await?;
+    if matches!(newest_key_age(&mut conn, algorithm).await?, Some(age) if age < rotation_seconds) {
+        return Ok(());
+    }
+
+    let (locked,): (Option<i64>,) = sqlx::query_as("SELECT GET_LOCK(?, ?)")
+        .bind(ROTATION_LOCK)
+        .bind(ROTATION_LOCK_WAIT_SECONDS)
+        .fetch_one(Traced(&mut *conn))
+        .await?;
+    if locked != Some(1) {
+        tracing::debug!("Another instance is rotating the JWT key");
+        return Ok(());
+    }
+    let rotated = rotate_locked(&mut conn, algorithm, rotation_seconds, retention_seconds).await;
+    let released = sqlx::query("SELECT RELEASE_LOCK(?)")
+        .bind(ROTATION_LOCK)
+        .execute(Traced(&mut *conn))
+        .await;
+    if let Err(e) = released {
+        // The lock belongs to the session; closing the connection frees it
+        tracing::warn!(error = %e, "Failed to release the JWT key rotation lock, closing the connection");
+        let _ = conn.close().await;
+    }
+    rotated
+}
+
+async fn rotate_locked(
+    conn: &mut MySqlConnection,
+    algorithm: Algorithm,
+    rotation_seconds: i64,
+    retention_seconds: i64,
+) -> anyhow::Result<()> {
+    // Another instance may have rotated while this one waited for the lock
+    if matches!(newest_key_age(conn, algorithm).await?, Some(age) if age < rotation_seconds) {
+        return Ok(());
+    }
+
+    // RSA key generation is CPU heavy
+    let (key, der) = tokio::task::spawn_blocking(move || JwtKey::generate(algorithm)).await??;
+    sqlx::query("INSERT INTO jwt_keys (kid, algorithm, private_key, created_at) VALUES (?, ?, ?, UTC_TIMESTAMP())")
+        .bind(&key.kid)
+        .bind(algorithm_name(algorithm))
+        .bind(&der)
+        .execute(Traced(&mut *conn))
+        .await?;
+    tracing::info!(kid = %key.kid, algorithm = %algorithm_name(algorithm), "Generated new JWT signing key");
+
+    // Keys past the retention can no longer have live tokens
+    let res = sqlx::query(
+        "DELETE FROM jwt_keys WHERE TIMESTAMPDIFF(SECOND, created_at, UTC_TIMESTAMP()) > ?",
+    )
+    .bind(retention_seconds)
+    .execute(Traced(&mut *conn))
+    .await?;
+    if res.rows_affected() > 0 {
+        tracing::info!(
+            removed = res.rows_affected(),
+            "Removed expired JWT signing keys"
+        );
+    }
+    Ok(())
+}
+
+/// Rotates the signing key if due and reloads all verification keys from MySQL.
+///
+/// A new key is published in the JWKS and accepted right away, but only
+/// signs once it is older than the refresh interval, by which time every
+/// instance has loaded it. Until then the previous key keeps signing.
+#[tracing::instrument(skip_all)]
+pub async fn refresh_keys(state: &AppState) -> anyhow::Result<()> {
+    if !state.jwt_keys.is_asymmetric() {
+        return Ok(());
+    }
+    let algorithm = state.config.jwt_algorithm;
+    let rotation_seconds = (state.config.jwt_key_rotation_hours * 3600) as i64;
+    let activation_seconds = state.config.jwt_key_refresh_seconds as i64;
+    let retention_seconds = key_retention_seconds(
+        rotation_seconds,
+        state.config.token_ttl_minutes * 60,
+        activation_seconds,
+    );
+    rotate_if_due(&state.db, algorithm, rotation_seconds, retention_seconds).await?;
+
+    let rows: Vec<(String, String, Vec<u8>, i64)> = sqlx::query_as("SELECT kid, algorithm, private_key, TIMESTAMPDIFF(SECOND, created_at, UTC_TIMESTAMP()) FROM jwt_keys WHERE TIMESTAMPDIFF(SECOND, created_at, UTC_TIMESTAMP()) <= ? ORDER BY created_at DESC")
+        .bind(retention_seconds)
+        .fetch_all(&state.db)
+        .await?;
+
+    let mut keys = Vec::new();
+    for (kid, alg, der, age) in rows {
+        let key_algorithm = match alg.parse::<Algorithm>() {
+            Ok(a) => a,
+            Err(_) => {
+                tracing::warn!(kid = %kid, algorithm = %alg, "Skipping JWT key with unknown algorithm");
+                continue;
+            }
+        };
+        let key = match JwtKey::from_private_der(kid.clone(), key_algorithm, &der) {
+            Ok(k) => k,
+            Err(e) => {
+                tracing::warn!(kid = %kid, error = %e, "Skipping unreadable JWT key");
+                continue;
+            }
+        };
+        keys.push((key, age));
+    }
+
+    let ages: Vec<(Algorithm, i64)> = keys.iter().map(|(k, age)| (k.algorithm, *age)).collect();
+    let Some(index) = signing_key_index(&ages, algorithm, activation_seconds) else {
+        anyhow::bail!(
+            "no JWT signing key available for {}",
+            algorithm_name(algorithm)
+        );
+    };
+    let (signing, _) = keys.remove(index);
+    state
+        .jwt_keys
+        .replace(Some(signing), keys.into_iter().map(|(k, _)| k).collect());
+    Ok(())
+}
+
+/// Picks the signing key from `(algorithm, age in seconds)` pairs sorted
+/// newest first: the newest key of `algorithm` at least `activation_seconds`
+/// old, or the newest one when none is (the first key of a new deployment).
+fn signing_key_index(
+    keys: &[(Algorithm, i64)],
+    algorithm: Algorithm,
+    activation_seconds: i64,
+) -> Option<usize> {
+    let candidates = || {
+        keys.iter()
+            .enumerate()
+            .filter(|(_, (a, _))| *a == algorithm)
+    };
+    candidates()
+        .find(|(_, (_, age))| *age >= activation_seconds)
+        .or_else(|| candidates().next())
+        .map(|(i, _)| i)
+}
+
+/// Periodically rotates and reloads keys so every instance picks up new ones.
+/// A refresh in progress at shutdown is allowed to finish.
+pub fn spawn_key_rotation(shutdown: &Shutdown, state: AppState) {
+    if !state.jwt_keys.is_asymmetric() {
+        return;
+    }
+    let period = Duration::from_secs(state.config.jwt_key_refresh_seconds);
+    let stop = shutdown.token();
+    shutdown.spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.tick().await;
+        loop {
+            tokio::select! {
+                _ = stop.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            if let Err(e) = refresh_keys(&state).await {
+                tracing::error!(error = %e, "JWT key refresh failed");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_outlive_tokens_signed_with_a_long_ttl() {
+        // 1h rotation, 5 min refresh, 1 day TTL: a key signs until about
+        // 1h10m after creation and its tokens stay valid a day after that
+        let retention = key_retention_seconds(3_600, 86_400, 300);
+        assert_eq!(retention, 3_600 + 600 + 86_400);
+        assert!(retention > 2 * 3_600);
+    }
+
+    #[test]
+    fn new_key_signs_only_after_the_activation_delay() {
+        // A key created 10s ago is published, the previous one keeps signing
+        let keys = [(Algorithm::EdDSA, 10), (Algorithm::EdDSA, 86_400)];
+        assert_eq!(signing_key_index(&keys, Algorithm::EdDSA, 300), Some(1));
+        // Once every instance has had time to load it, the new key signs
+        let keys = [(Algorithm::EdDSA, 300), (Algorithm::EdDSA, 86_700)];
+        assert_eq!(signing_key_index(&keys, Algorithm::EdDSA, 300), Some(0));
+    }
+
+    #[test]
+    fn first_key_signs_immediately() {
+        assert_eq!(
+            signing_key_index(&[(Algorithm::EdDSA, 0)], Algorithm::EdDSA, 300),
+            Some(0)
+        );
+        assert_eq!(signing_key_index(&[], Algorithm::EdDSA, 300), None);
+    }
+
+    #[test]
+    fn only_keys_of_the_configured_algorithm_sign() {
+        let keys = [
+            (Algorithm::RS256, 10),
+            (Algorithm::EdDSA, 5),
+            (Algorithm::RS256, 86_400),
+        ];
+        assert_eq!(signing_key_index(&keys, Algorithm::RS256, 300), Some(2));
+        assert_eq!(signing_key_index(&keys, Algorithm::EdDSA, 300), Some(1));
+        assert_eq!(signing_key_index(&keys[..1], Algorithm::EdDSA, 300), None);
+    }
+}
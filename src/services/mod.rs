@@ -1,2 +1,12 @@
+pub mod access_token_service;
+pub mod admin_service;
+pub mod audit_service;
 pub mod auth_service;
-pub mod note_service;
\ No newline at end of file
+pub mod health_service;
+pub mod jwt_key_service;
+pub mod note_service;
+pub mod oidc_service;
+pub mod passkey_service;
+pub mod preference_service;
+pub mod session_service;
+pub mod totp_service;
@@ -27,13 +27,14 @@ impl From<sqlx::Error> for NoteError {
     }
 }
 
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
 pub async fn create_note(
     state: &AppState,
     user_id: Uuid,
     req: CreateNoteRequest,
 ) -> Result<Note, NoteError> {
     let note_id = Uuid::new_v4();
-    sqlx::query("INSERT INTO notes (id, user_id, title, content, category, tags, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, CONVERT_TZ(UTC_TIMESTAMP(), '+00:00', '+08:00'), CONVERT_TZ(UTC_TIMESTAMP(), '+00:00', '+08:00'))")
+    sqlx::query("INSERT INTO notes (id, user_id, title, content, category, tags, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, UTC_TIMESTAMP(), UTC_TIMESTAMP())")
         .bind(note_id)
         .bind(user_id)
         .bind(&req.title)
@@ -42,6 +43,7 @@ pub async fn create_note(
         .bind(&req.tags)
         .execute(&state.db)
         .await?;
+    metrics::counter!("notes_created_total").increment(1);
 
     let note = sqlx::query_as::<_, Note>(
         "SELECT id, user_id, title, content, category, tags, created_at, updated_at FROM notes WHERE id = ?",
@@ -52,6 +54,7 @@ pub async fn create_note(
     Ok(note)
 }
 
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
 pub async fn list_notes(
     state: &AppState,
     user_id: Uuid,
@@ -79,6 +82,7 @@ pub async fn list_notes(
     Ok(notes)
 }
 
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
 pub async fn get_note(state: &AppState, user_id: Uuid, note_id: Uuid) -> Result<Note, NoteError> {
     let note = sqlx::query_as::<_, Note>("SELECT id, user_id, title, content, category, tags, created_at, updated_at FROM notes WHERE id = ? AND user_id = ?")
         .bind(note_id)
@@ -91,6 +95,7 @@ pub async fn get_note(state: &AppState, user_id: Uuid, note_id: Uuid) -> Result<
     }
 }
 
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
 pub async fn update_note(
     state: &AppState,
     user_id: Uuid,
@@ -103,7 +108,7 @@ pub async fn update_note(
     let content = req.content.unwrap_or(current.content);
     let tags = req.tags.or(current.tags);
 
-    sqlx::query("UPDATE notes SET title = ?, content = ?, tags = ?, updated_at = CONVERT_TZ(UTC_TIMESTAMP(), '+00:00', '+08:00') WHERE id = ? AND user_id = ?")
+    sqlx::query("UPDATE notes SET title = ?, content = ?, tags = ?, updated_at = UTC_TIMESTAMP() WHERE id = ? AND user_id = ?")
         .bind(&title)
         .bind(&content)
         .bind(&tags)
@@ -114,6 +119,7 @@ pub async fn update_note(
     get_note(state, user_id, note_id).await
 }
 
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
 pub async fn delete_note(state: &AppState, user_id: Uuid, note_id: Uuid) -> Result<(), NoteError> {
     let res = sqlx::query("DELETE FROM notes WHERE id = ? AND user_id = ?")
         .bind(note_id)
@@ -0,0 +1,995 @@
+use crate::{
+    app_middleware::client_info::ClientInfo,
+    config::OidcProviderConfig,
+    db::redis::{store_json, take_json, RedisError},
+    models::{
+        audit::LoginMethod,
+        oidc::OidcCallbackQuery,
+        user::{normalize_email, LoginOutcome, User},
+    },
+    services::auth_service::{complete_login, AuthError},
+    utils::session_cookie::csrf_matches,
+    AppState,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+/// How long the user has to complete the login at the identity provider.
+pub const AUTH_REQUEST_TTL_SECONDS: u64 = 600;
+
+/// How long a provider's discovery document and JWKS are reused.
+const PROVIDER_CACHE_TTL_SECONDS: u64 = 3600;
+
+#[derive(Debug)]
+pub enum OidcError {
+    UnknownProvider(String),
+    InvalidState,
+    Provider(String),
+    InvalidIdToken(String),
+    EmailRequired,
+    EmailNotVerified,
+    Http(reqwest::Error),
+    Db(sqlx::Error),
+    AccountDisabled,
+    Redis(RedisError),
+    Internal(anyhow::Error),
+}
+
+impl std::fmt::Display for OidcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OidcError::UnknownProvider(name) => write!(f, "unknown identity provider: {}", name),
+            OidcError::InvalidState => write!(
+                f,
+                "login request expired or state mismatch, please try again"
+            ),
+            OidcError::Provider(e) => write!(f, "identity provider error: {}", e),
+            OidcError::InvalidIdToken(e) => write!(f, "invalid id token: {}", e),
+            OidcError::EmailRequired => {
+                write!(f, "identity provider did not return an email address")
+            }
+            OidcError::EmailNotVerified => {
+                write!(f, "identity provider did not verify the email address")
+            }
+            OidcError::Http(e) => write!(f, "identity provider request failed: {}", e),
+            OidcError::Db(e) => write!(f, "db error: {}", e),
+            OidcError::AccountDisabled => write!(f, "account is disabled"),
+            OidcError::Redis(e) => write!(f, "{}", e),
+            OidcError::Internal(e) => write!(f, "internal error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for OidcError {}
+
+impl From<reqwest::Error> for OidcError {
+    fn from(e: reqwest::Error) -> Self {
+        OidcError::Http(e)
+    }
+}
+
+impl From<sqlx::Error> for OidcError {
+    fn from(e: sqlx::Error) -> Self {
+        OidcError::Db(e)
+    }
+}
+
+impl From<RedisError> for OidcError {
+    fn from(e: RedisError) -> Self {
+        OidcError::Redis(e)
+    }
+}
+
+impl From<anyhow::Error> for OidcError {
+    fn from(e: anyhow::Error) -> Self {
+        OidcError::Internal(e)
+    }
+}
+
+impl From<AuthError> for OidcError {
+    fn from(e: AuthError) -> Self {
+        match e {
+            AuthError::Db(e) => OidcError::Db(e),
+            AuthError::AccountDisabled => OidcError::AccountDisabled,
+            other => OidcError::Internal(anyhow::anyhow!(other.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+    preferred_username: Option<String>,
+    name: Option<String>,
+}
+
+/// Authorization request kept in Redis between the redirect and the callback.
+#[derive(Serialize, Deserialize)]
+struct PendingAuthorization {
+    provider: String,
+    nonce: String,
+    code_verifier: String,
+    #[serde(default)]
+    session_cookie: bool,
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn find_provider<'a>(state: &'a AppState, name: &str) -> Result<&'a OidcProviderConfig, OidcError> {
+    state
+        .config
+        .oidc_providers
+        .iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| OidcError::UnknownProvider(name.to_string()))
+}
+
+async fn discover(
+    http: &reqwest::Client,
+    provider: &OidcProviderConfig,
+) -> Result<ProviderMetadata, OidcError> {
+    let url = format!("{}/.well-known/openid-configuration", provider.issuer);
+    let metadata = http
+        .get(&url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<ProviderMetadata>()
+        .await?;
+    // ID tokens are checked against this issuer, so it must be the configured one
+    if metadata.issuer.trim_end_matches('/') != provider.issuer {
+        return Err(OidcError::Provider(format!(
+            "discovery document issuer {} does not match the configured issuer {}",
+            metadata.issuer, provider.issuer
+        )));
+    }
+    Ok(metadata)
+}
+
+struct CachedProvider {
+    metadata: Arc<ProviderMetadata>,
+    fetched_at: Instant,
+    jwks: Option<Arc<JwkSet>>,
+}
+
+/// Discovery documents and JWKS per provider, so a sign-in doesn't fetch
+/// them again and logins keep working through short provider outages. The
+/// JWKS is refetched early when a token names a key it doesn't contain.
+#[derive(Clone, Default)]
+pub struct ProviderCache {
+    providers: Arc<RwLock<HashMap<String, CachedProvider>>>,
+}
+
+impl ProviderCache {
+    async fn metadata(
+        &self,
+        http: &reqwest::Client,
+        provider: &OidcProviderConfig,
+    ) -> Result<Arc<ProviderMetadata>, OidcError> {
+        let ttl = Duration::from_secs(PROVIDER_CACHE_TTL_SECONDS);
+        if let Some(cached) = self.providers.read().unwrap().get(&provider.name) {
+            if cached.fetched_at.elapsed() < ttl {
+                return Ok(cached.metadata.clone());
+            }
+        }
+        let metadata = Arc::new(discover(http, provider).await?);
+        self.providers.write().unwrap().insert(
+            provider.name.clone(),
+            CachedProvider {
+                metadata: metadata.clone(),
+                fetched_at: Instant::now(),
+                jwks: None,
+            },
+        );
+        Ok(metadata)
+    }
+
+    /// The provider key with this `kid` (any key if the token names none).
+    async fn signing_key(
+        &self,
+        http: &reqwest::Client,
+        provider: &OidcProviderConfig,
+        metadata: &ProviderMetadata,
+        kid: Option<&str>,
+    ) -> Result<Jwk, OidcError> {
+        let find = |jwks: &JwkSet| {
+            match kid {
+                Some(kid) => jwks.find(kid),
+                None => jwks.keys.first(),
+            }
+            .cloned()
+        };
+        let cached = self
+            .providers
+            .read()
+            .unwrap()
+            .get(&provider.name)
+            .and_then(|p| p.jwks.clone());
+        if let Some(key) = cached.as_deref().and_then(find) {
+            return Ok(key);
+        }
+
+        let jwks = http
+            .get(&metadata.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<JwkSet>()
+            .await?;
+        let key = find(&jwks);
+        if let Some(cached) = self.providers.write().unwrap().get_mut(&provider.name) {
+            cached.jwks = Some(Arc::new(jwks));
+        }
+        key.ok_or_else(|| {
+            OidcError::InvalidIdToken("signing key not found in provider JWKS".to_string())
+        })
+    }
+}
+
+pub fn provider_names(state: &AppState) -> Vec<String> {
+    state
+        .config
+        .oidc_providers
+        .iter()
+        .map(|p| p.name.clone())
+        .collect()
+}
+
+/// Builds the authorization-code + PKCE redirect URL for a provider. Also
+/// returns the `state`, which the caller binds to the browser with a cookie.
+#[tracing::instrument(skip_all)]
+pub async fn authorization_url(
+    state: &AppState,
+    provider_name: &str,
+    session_cookie: bool,
+) -> Result<(String, String), OidcError> {
+    let provider = find_provider(state, provider_name)?;
+    let metadata = state.oidc.metadata(&state.http, provider).await?;
+
+    let csrf_state = random_string(32);
+    let nonce = random_string(32);
+    let code_verifier = random_string(64);
+    let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+    let pending = PendingAuthorization {
+        provider: provider.name.clone(),
+        nonce: nonce.clone(),
+        code_verifier,
+        session_cookie,
+    };
+    store_json(
+        &state.redis,
+        &format!("oidc:{}", csrf_state),
+        &pending,
+        AUTH_REQUEST_TTL_SECONDS,
+    )
+    .await?;
+
+    let url = reqwest::Url::parse_with_params(
+        &metadata.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", provider.client_id.as_str()),
+            ("redirect_uri", provider.redirect_url.as_str()),
+            ("scope", provider.scopes.as_str()),
+            ("state", csrf_state.as_str()),
+            ("nonce", nonce.as_str()),
+            ("code_challenge", code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|e| OidcError::Provider(format!("invalid authorization endpoint: {}", e)))?;
+    Ok((url.to_string(), csrf_state))
+}
+
+async fn exchange_code(
+    http: &reqwest::Client,
+    provider: &OidcProviderConfig,
+    metadata: &ProviderMetadata,
+    code: &str,
+    code_verifier: &str,
+) -> Result<String, OidcError> {
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", provider.redirect_url.as_str()),
+        ("client_id", provider.client_id.as_str()),
+        ("code_verifier", code_verifier),
+    ];
+    if let Some(secret) = provider.client_secret.as_deref() {
+        form.push(("client_secret", secret));
+    }
+
+    let resp = http
+        .post(&metadata.token_endpoint)
+        .form(&form)
+        .send()
+        .await?;
+    let status = resp.status();
+    let body: TokenResponse = resp.json().await?;
+    if let Some(error) = body.error {
+        let description = body.error_description.unwrap_or_default();
+        return Err(OidcError::Provider(
+            format!("{} {}", error, description).trim().to_string(),
+        ));
+    }
+    if !status.is_success() {
+        return Err(OidcError::Provider(format!(
+            "token endpoint returned {}",
+            status
+        )));
+    }
+    body.id_token
+        .ok_or_else(|| OidcError::Provider("token response has no id_token".to_string()))
+}
+
+async fn verify_id_token(
+    http: &reqwest::Client,
+    cache: &ProviderCache,
+    provider: &OidcProviderConfig,
+    metadata: &ProviderMetadata,
+    id_token: &str,
+    expected_nonce: &str,
+) -> Result<IdTokenClaims, OidcError> {
+    let header = decode_header(id_token).map_err(|e| OidcError::InvalidIdToken(e.to_string()))?;
+    if matches!(
+        header.alg,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    ) {
+        return Err(OidcError::InvalidIdToken(
+            "symmetric signatures are not accepted".to_string(),
+        ));
+    }
+
+    let jwk = cache
+        .signing_key(http, provider, metadata, header.kid.as_deref())
+        .await?;
+    let key = DecodingKey::from_jwk(&jwk).map_err(|e| OidcError::InvalidIdToken(e.to_string()))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&metadata.issuer]);
+    validation.set_audience(&[&provider.client_id]);
+    let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+        .map_err(|e| OidcError::InvalidIdToken(e.to_string()))?
+        .claims;
+
+    if claims.nonce.as_deref() != Some(expected_nonce) {
+        return Err(OidcError::InvalidIdToken("nonce mismatch".to_string()));
+    }
+    Ok(claims)
+}
+
+async fn fetch_user(state: &AppState, user_id: Uuid) -> Result<User, OidcError> {
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, username, email, password_hash, CAST(created_at AS DATETIME) AS created_at FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&state.db)
+        .await?;
+    Ok(user)
+}
+
+async fn link_identity(
+    state: &AppState,
+    provider: &str,
+    claims: &IdTokenClaims,
+    user_id: Uuid,
+) -> Result<(), OidcError> {
+    sqlx::query("INSERT INTO user_identities (provider, subject, user_id, email, created_at) VALUES (?, ?, ?, ?, UTC_TIMESTAMP())")
+        .bind(provider)
+        .bind(&claims.sub)
+        .bind(user_id)
+        .bind(&claims.email)
+        .execute(&state.db)
+        .await?;
+    Ok(())
+}
+
+/// Picks a free username based on what the provider tells us about the user.
+async fn available_username(
+    state: &AppState,
+    claims: &IdTokenClaims,
+    email: &str,
+) -> Result<String, OidcError> {
+    let base: String = claims
+        .preferred_username
+        .as_deref()
+        .or(claims.name.as_deref())
+        .unwrap_or_else(|| email.split('@').next().unwrap_or(email))
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        .take(48)
+        .collect();
+    let base = if base.is_empty() {
+        "user".to_string()
+    } else {
+        base
+    };
+
+    let mut candidate = base.clone();
+    loop {
+        let taken: Option<(i64,)> =
+            sqlx::query_as("SELECT 1 FROM users WHERE username = ? LIMIT 1")
+                .bind(&candidate)
+                .fetch_optional(&state.db)
+                .await?;
+        if taken.is_none() {
+            return Ok(candidate);
+        }
+        candidate = format!("{}-{}", base, random_string(6).to_lowercase());
+    }
+}
+
+/// The normalized email of a new identity. It links to or creates an account
+/// with that address, so the provider must have verified it; otherwise anyone
+/// could claim someone else's address.
+fn verified_email(claims: &IdTokenClaims) -> Result<String, OidcError> {
+    let email = claims.email.as_deref().ok_or(OidcError::EmailRequired)?;
+    if !claims.email_verified {
+        return Err(OidcError::EmailNotVerified);
+    }
+    Ok(normalize_email(email))
+}
+
+/// Finds the local account for an external identity: an existing link, an
+/// existing user with the same verified email, or a newly provisioned user.
+async fn resolve_user(
+    state: &AppState,
+    provider: &str,
+    claims: &IdTokenClaims,
+) -> Result<User, OidcError> {
+    let linked: Option<(Uuid,)> =
+        sqlx::query_as("SELECT user_id FROM user_identities WHERE provider = ? AND subject = ?")
+            .bind(provider)
+            .bind(&claims.sub)
+            .fetch_optional(&state.db)
+            .await?;
+    if let Some((user_id,)) = linked {
+        return fetch_user(state, user_id).await;
+    }
+
+    let email = verified_email(claims)?;
+    let email = email.as_str();
+    let existing: Option<(Uuid,)> = sqlx::query_as("SELECT id FROM users WHERE email = ? LIMIT 1")
+        .bind(email)
+        .fetch_optional(&state.db)
+        .await?;
+    if let Some((user_id,)) = existing {
+        link_identity(state, provider, claims, user_id).await?;
+        tracing::info!(user_id = %user_id, provider = %provider, "Linked external identity to existing user");
+        return fetch_user(state, user_id).await;
+    }
+
+    let user_id = Uuid::new_v4();
+    let username = available_username(state, claims, email).await?;
+    // Provisioned accounts sign in through the provider; the random password is never disclosed
+    let password_hash = state.passwords.hash(&random_string(32))?;
+    sqlx::query("INSERT INTO users (id, username, email, password_hash, created_at) VALUES (?, ?, ?, ?, UTC_TIMESTAMP())")
+        .bind(user_id)
+        .bind(&username)
+        .bind(email)
+        .bind(&password_hash)
+        .execute(&state.db)
+        .await?;
+    link_identity(state, provider, claims, user_id).await?;
+    tracing::info!(user_id = %user_id, provider = %provider, username = %username, "Provisioned user from external identity");
+    fetch_user(state, user_id).await
+}
+
+/// The code and state of a callback, or the error the provider redirected with.
+fn callback_params(query: OidcCallbackQuery) -> Result<(String, String), OidcError> {
+    if let Some(error) = query.error {
+        let description = query.error_description.unwrap_or_default();
+        return Err(OidcError::Provider(
+            format!("{} {}", error, description).trim().to_string(),
+        ));
+    }
+    match (query.code, query.state) {
+        (Some(code), Some(csrf_state)) => Ok((code, csrf_state)),
+        _ => Err(OidcError::InvalidState),
+    }
+}
+
+/// The callback must come back to the browser that started the login, or an
+/// attacker could have a victim complete the attacker's login (login CSRF).
+fn check_browser_state(csrf_state: &str, cookie: Option<&str>) -> Result<(), OidcError> {
+    match cookie {
+        Some(cookie) if csrf_matches(cookie, csrf_state) => Ok(()),
+        _ => Err(OidcError::InvalidState),
+    }
+}
+
+/// The authorization request stored under the callback's state; it must
+/// exist and have been started for the provider that is calling back.
+fn match_pending(
+    pending: Option<PendingAuthorization>,
+    provider_name: &str,
+) -> Result<PendingAuthorization, OidcError> {
+    match pending {
+        Some(pending) if pending.provider == provider_name => Ok(pending),
+        _ => Err(OidcError::InvalidState),
+    }
+}
+
+/// Completes the login, or returns a TOTP challenge when the account has
+/// two-factor authentication enabled; the flag tells whether the client
+/// asked for a session cookie. `browser_state` is the value of the state
+/// cookie set when the login started.
+#[tracing::instrument(skip_all)]
+pub async fn handle_callback(
+    state: &AppState,
+    provider_name: &str,
+    query: OidcCallbackQuery,
+    browser_state: Option<&str>,
+    client: &ClientInfo,
+) -> Result<(LoginOutcome, bool), OidcError> {
+    let (code, csrf_state) = callback_params(query)?;
+    check_browser_state(&csrf_state, browser_state)?;
+    let pending = take_json(&state.redis, &format!("oidc:{}", csrf_state)).await?;
+    let pending = match_pending(pending, provider_name)?;
+
+    let provider = find_provider(state, provider_name)?;
+    let metadata = state.oidc.metadata(&state.http, provider).await?;
+    let id_token = exchange_code(
+        &state.http,
+        provider,
+        &metadata,
+        &code,
+        &pending.code_verifier,
+    )
+    .await?;
+    let claims = verify_id_token(
+        &state.http,
+        &state.oidc,
+        provider,
+        &metadata,
+        &id_token,
+        &pending.nonce,
+    )
+    .await?;
+
+    // The provider only replaces the password: accounts with TOTP still need a code
+    let user = resolve_user(state, &provider.name, &claims).await?;
+    Ok((
+        complete_login(state, &user, client, LoginMethod::Oidc).await?,
+        pending.session_cookie,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        extract::State,
+        response::IntoResponse,
+        routing::{get, post},
+        Form, Json, Router,
+    };
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use serde_json::{json, Value};
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    };
+
+    const CLIENT_ID: &str = "tiny-note";
+    const CLIENT_SECRET: &str = "client-secret";
+    const REDIRECT_URL: &str = "http://localhost:8080/api/tiny-note/auth/oidc/mock/callback";
+    const CODE: &str = "auth-code";
+    const CODE_VERIFIER: &str = "code-verifier";
+    const NONCE: &str = "nonce-value";
+
+    /// What the stub identity provider serves; tests change it between calls.
+    #[derive(Clone, Default)]
+    struct Served {
+        discovery_issuer: Arc<Mutex<String>>,
+        id_token: Arc<Mutex<String>>,
+        jwks: Arc<Mutex<Value>>,
+        discovery_requests: Arc<AtomicUsize>,
+        jwks_requests: Arc<AtomicUsize>,
+    }
+
+    /// Stub OpenID provider with discovery, JWKS and token endpoints, signing
+    /// ID tokens with an Ed25519 key.
+    struct MockProvider {
+        issuer: String,
+        /// Key ID and key the next ID token is signed with
+        signing: Mutex<(String, EncodingKey)>,
+        served: Served,
+        cache: ProviderCache,
+    }
+
+    /// A new Ed25519 signing key and its public JWK.
+    fn ed25519_key(kid: &str) -> (EncodingKey, Value) {
+        let der = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(der.as_ref()).unwrap();
+        let x = URL_SAFE_NO_PAD.encode(pair.public_key().as_ref());
+        let jwk = json!({ "kty": "OKP", "crv": "Ed25519", "x": x, "kid": kid, "alg": "EdDSA", "use": "sig" });
+        (EncodingKey::from_ed_der(der.as_ref()), jwk)
+    }
+
+    impl MockProvider {
+        async fn start() -> Self {
+            let (encoding, jwk) = ed25519_key("key-1");
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let issuer = format!("http://{}", listener.local_addr().unwrap());
+            let served = Served::default();
+            *served.discovery_issuer.lock().unwrap() = issuer.clone();
+            *served.jwks.lock().unwrap() = json!({ "keys": [jwk] });
+            let app = Router::new()
+                .route("/.well-known/openid-configuration", get(discovery))
+                .route("/jwks", get(jwks))
+                .route("/token", post(token))
+                .with_state(served.clone());
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+            MockProvider {
+                issuer,
+                signing: Mutex::new(("key-1".to_string(), encoding)),
+                served,
+                cache: ProviderCache::default(),
+            }
+        }
+
+        /// Replaces the signing key, publishing only the new one in the JWKS.
+        fn rotate_key(&self, kid: &str) {
+            let (encoding, jwk) = ed25519_key(kid);
+            *self.signing.lock().unwrap() = (kid.to_string(), encoding);
+            *self.served.jwks.lock().unwrap() = json!({ "keys": [jwk] });
+        }
+
+        fn config(&self) -> OidcProviderConfig {
+            OidcProviderConfig {
+                name: "mock".to_string(),
+                issuer: self.issuer.clone(),
+                client_id: CLIENT_ID.to_string(),
+                client_secret: Some(CLIENT_SECRET.to_string()),
+                redirect_url: REDIRECT_URL.to_string(),
+                scopes: "openid email profile".to_string(),
+            }
+        }
+
+        /// Claims of a valid ID token for the login started with [`NONCE`].
+        fn claims(&self) -> Value {
+            json!({
+                "iss": self.issuer,
+                "aud": CLIENT_ID,
+                "sub": "user-1",
+                "nonce": NONCE,
+                "email": "alice@example.com",
+                "email_verified": true,
+                "iat": chrono::Utc::now().timestamp(),
+                "exp": chrono::Utc::now().timestamp() + 300,
+            })
+        }
+
+        /// Makes the token endpoint return an ID token with these claims.
+        fn issue(&self, claims: Value) {
+            let (kid, encoding) = &*self.signing.lock().unwrap();
+            let mut header = Header::new(Algorithm::EdDSA);
+            header.kid = Some(kid.clone());
+            *self.served.id_token.lock().unwrap() = encode(&header, &claims, encoding).unwrap();
+        }
+
+        /// Runs discovery, code exchange and ID token verification like the callback does.
+        async fn login(
+            &self,
+            code_verifier: &str,
+            nonce: &str,
+        ) -> Result<IdTokenClaims, OidcError> {
+            let http = reqwest::Client::new();
+            let provider = self.config();
+            let metadata = self.cache.metadata(&http, &provider).await?;
+            let id_token = exchange_code(&http, &provider, &metadata, CODE, code_verifier).await?;
+            verify_id_token(&http, &self.cache, &provider, &metadata, &id_token, nonce).await
+        }
+
+        fn requests(&self) -> (usize, usize) {
+            (
+                self.served.discovery_requests.load(Ordering::SeqCst),
+                self.served.jwks_requests.load(Ordering::SeqCst),
+            )
+        }
+    }
+
+    async fn jwks(State(served): State<Served>) -> Json<Value> {
+        served.jwks_requests.fetch_add(1, Ordering::SeqCst);
+        Json(served.jwks.lock().unwrap().clone())
+    }
+
+    async fn discovery(
+        State(served): State<Served>,
+        headers: axum::http::HeaderMap,
+    ) -> Json<Value> {
+        served.discovery_requests.fetch_add(1, Ordering::SeqCst);
+        let base = format!("http://{}", headers["host"].to_str().unwrap());
+        Json(json!({
+            "issuer": *served.discovery_issuer.lock().unwrap(),
+            "authorization_endpoint": format!("{}/authorize", base),
+            "token_endpoint": format!("{}/token", base),
+            "jwks_uri": format!("{}/jwks", base),
+        }))
+    }
+
+    async fn token(
+        State(served): State<Served>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> axum::response::Response {
+        let expected = [
+            ("grant_type", "authorization_code"),
+            ("code", CODE),
+            ("code_verifier", CODE_VERIFIER),
+            ("client_id", CLIENT_ID),
+            ("client_secret", CLIENT_SECRET),
+            ("redirect_uri", REDIRECT_URL),
+        ];
+        if expected
+            .iter()
+            .any(|(k, v)| form.get(*k).map(String::as_str) != Some(*v))
+        {
+            let body =
+                json!({ "error": "invalid_grant", "error_description": "bad code or verifier" });
+            return (axum::http::StatusCode::BAD_REQUEST, Json(body)).into_response();
+        }
+        let id_token = served.id_token.lock().unwrap().clone();
+        Json(json!({ "access_token": "at", "token_type": "Bearer", "id_token": id_token }))
+            .into_response()
+    }
+
+    fn pending(provider: &str) -> PendingAuthorization {
+        PendingAuthorization {
+            provider: provider.to_string(),
+            nonce: NONCE.to_string(),
+            code_verifier: CODE_VERIFIER.to_string(),
+            session_cookie: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn exchanges_code_and_verifies_id_token() {
+        let mock = MockProvider::start().await;
+        mock.issue(mock.claims());
+        let claims = mock.login(CODE_VERIFIER, NONCE).await.unwrap();
+        assert_eq!(claims.sub, "user-1");
+        assert_eq!(claims.email.as_deref(), Some("alice@example.com"));
+        assert!(claims.email_verified);
+    }
+
+    #[tokio::test]
+    async fn reuses_discovery_and_jwks_across_logins() {
+        let mock = MockProvider::start().await;
+        mock.issue(mock.claims());
+        mock.login(CODE_VERIFIER, NONCE).await.unwrap();
+        mock.login(CODE_VERIFIER, NONCE).await.unwrap();
+        assert_eq!(mock.requests(), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn refetches_jwks_for_an_unknown_key_id() {
+        let mock = MockProvider::start().await;
+        mock.issue(mock.claims());
+        mock.login(CODE_VERIFIER, NONCE).await.unwrap();
+
+        mock.rotate_key("key-2");
+        mock.issue(mock.claims());
+        mock.login(CODE_VERIFIER, NONCE).await.unwrap();
+        assert_eq!(mock.requests(), (1, 2));
+
+        // A key the provider doesn't publish is still rejected
+        *mock.signing.lock().unwrap() = ("key-3".to_string(), ed25519_key("key-3").0);
+        mock.issue(mock.claims());
+        assert!(matches!(
+            mock.login(CODE_VERIFIER, NONCE).await,
+            Err(OidcError::InvalidIdToken(_))
+        ));
+        assert_eq!(mock.requests(), (1, 3));
+    }
+
+    #[tokio::test]
+    async fn rejects_code_exchange_with_wrong_verifier() {
+        let mock = MockProvider::start().await;
+        mock.issue(mock.claims());
+        let err = mock.login("another-verifier", NONCE).await.unwrap_err();
+        assert!(
+            matches!(&err, OidcError::Provider(e) if e.starts_with("invalid_grant")),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_nonce_mismatch() {
+        let mock = MockProvider::start().await;
+        mock.issue(mock.claims());
+        let err = mock
+            .login(CODE_VERIFIER, "nonce-of-another-login")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, OidcError::InvalidIdToken(e) if e == "nonce mismatch"),
+            "{}",
+            err
+        );
+
+        let mut claims = mock.claims();
+        claims.as_object_mut().unwrap().remove("nonce");
+        mock.issue(claims);
+        assert!(matches!(
+            mock.login(CODE_VERIFIER, NONCE).await,
+            Err(OidcError::InvalidIdToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_discovery_issuer_mismatch() {
+        let mock = MockProvider::start().await;
+        mock.issue(mock.claims());
+        *mock.served.discovery_issuer.lock().unwrap() = "https://attacker.example".to_string();
+        let err = mock.login(CODE_VERIFIER, NONCE).await.unwrap_err();
+        assert!(
+            matches!(&err, OidcError::Provider(e) if e.contains("does not match the configured issuer")),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn accepts_discovery_issuer_with_trailing_slash() {
+        let mock = MockProvider::start().await;
+        let issuer = format!("{}/", mock.issuer);
+        *mock.served.discovery_issuer.lock().unwrap() = issuer.clone();
+        let mut claims = mock.claims();
+        claims["iss"] = json!(issuer);
+        mock.issue(claims);
+        assert!(mock.login(CODE_VERIFIER, NONCE).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn rejects_id_token_issuer_mismatch() {
+        let mock = MockProvider::start().await;
+        let mut claims = mock.claims();
+        claims["iss"] = json!("https://attacker.example");
+        mock.issue(claims);
+        assert!(matches!(
+            mock.login(CODE_VERIFIER, NONCE).await,
+            Err(OidcError::InvalidIdToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_audience_mismatch() {
+        let mock = MockProvider::start().await;
+        let mut claims = mock.claims();
+        claims["aud"] = json!("another-client");
+        mock.issue(claims);
+        assert!(matches!(
+            mock.login(CODE_VERIFIER, NONCE).await,
+            Err(OidcError::InvalidIdToken(_))
+        ));
+    }
+
+    #[test]
+    fn callback_requires_code_and_state() {
+        let query =
+            |code: Option<&str>, state: Option<&str>, error: Option<&str>| OidcCallbackQuery {
+                code: code.map(str::to_string),
+                state: state.map(str::to_string),
+                error: error.map(str::to_string),
+                error_description: None,
+            };
+        assert_eq!(
+            callback_params(query(Some("c"), Some("s"), None)).unwrap(),
+            ("c".to_string(), "s".to_string())
+        );
+        assert!(matches!(
+            callback_params(query(Some("c"), None, None)),
+            Err(OidcError::InvalidState)
+        ));
+        assert!(matches!(
+            callback_params(query(None, Some("s"), None)),
+            Err(OidcError::InvalidState)
+        ));
+        assert!(matches!(
+            callback_params(query(Some("c"), Some("s"), Some("access_denied"))),
+            Err(OidcError::Provider(_))
+        ));
+    }
+
+    #[test]
+    fn state_must_match_a_pending_login_for_the_provider() {
+        assert!(match_pending(Some(pending("mock")), "mock").is_ok());
+        // Unknown, expired or already used state
+        assert!(matches!(
+            match_pending(None, "mock"),
+            Err(OidcError::InvalidState)
+        ));
+        // Login started for one provider, callback arriving at another
+        assert!(matches!(
+            match_pending(Some(pending("other")), "mock"),
+            Err(OidcError::InvalidState)
+        ));
+    }
+
+    #[test]
+    fn state_must_come_from_the_browser_that_started_the_login() {
+        assert!(check_browser_state("state-1", Some("state-1")).is_ok());
+        // Callback opened in a browser that never started a login
+        assert!(matches!(
+            check_browser_state("state-1", None),
+            Err(OidcError::InvalidState)
+        ));
+        // Attacker's callback URL opened in a browser with its own login pending
+        assert!(matches!(
+            check_browser_state("state-1", Some("state-2")),
+            Err(OidcError::InvalidState)
+        ));
+        assert!(matches!(
+            check_browser_state("", Some("")),
+            Err(OidcError::InvalidState)
+        ));
+    }
+
+    #[test]
+    fn new_identities_need_a_verified_email() {
+        let claims = |email: Option<&str>, email_verified: bool| IdTokenClaims {
+            sub: "user-1".to_string(),
+            nonce: Some(NONCE.to_string()),
+            email: email.map(str::to_string),
+            email_verified,
+            preferred_username: None,
+            name: None,
+        };
+        assert_eq!(
+            verified_email(&claims(Some(" Alice@Example.com"), true)).unwrap(),
+            "alice@example.com"
+        );
+        assert!(matches!(
+            verified_email(&claims(Some("alice@example.com"), false)),
+            Err(OidcError::EmailNotVerified)
+        ));
+        assert!(matches!(
+            verified_email(&claims(None, true)),
+            Err(OidcError::EmailRequired)
+        ));
+    }
+}
@@ -0,0 +1,359 @@
+use crate::{
+    app_middleware::client_info::ClientInfo,
+    config::Config,
+    db::redis::{store_json, take_json, RedisError},
+    models::{
+        audit::{AuditEventType, LoginMethod},
+        passkey::{
+            FinishPasskeyLoginRequest, FinishPasskeyRegistrationRequest, PasskeyInfo,
+            StartPasskeyLoginRequest, StartPasskeyLoginResponse,
+        },
+        user::{LoginResponse, User},
+    },
+    services::{
+        audit_service,
+        auth_service::{
+            find_user_by_identifier, issue_login_response, record_login_failure, AuthError,
+        },
+    },
+    AppState,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use webauthn_rs::prelude::{
+    CreationChallengeResponse, Passkey, PasskeyAuthentication, PasskeyRegistration, Url, Webauthn,
+    WebauthnBuilder, WebauthnError,
+};
+
+/// How long a started registration or login ceremony stays valid.
+const CEREMONY_TTL_SECONDS: u64 = 300;
+
+#[derive(Debug)]
+pub enum PasskeyError {
+    NotFound,
+    NoPasskeys,
+    ChallengeExpired,
+    AlreadyRegistered,
+    Verification(WebauthnError),
+    Db(sqlx::Error),
+    AccountDisabled,
+    Redis(RedisError),
+    Internal(anyhow::Error),
+}
+
+impl std::fmt::Display for PasskeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasskeyError::NotFound => write!(f, "passkey not found"),
+            PasskeyError::NoPasskeys => write!(f, "no passkey registered for this account"),
+            PasskeyError::ChallengeExpired => {
+                write!(f, "passkey challenge expired, please try again")
+            }
+            PasskeyError::AlreadyRegistered => write!(f, "passkey is already registered"),
+            PasskeyError::Verification(e) => write!(f, "passkey verification failed: {}", e),
+            PasskeyError::Db(e) => write!(f, "db error: {}", e),
+            PasskeyError::AccountDisabled => write!(f, "account is disabled"),
+            PasskeyError::Redis(e) => write!(f, "{}", e),
+            PasskeyError::Internal(e) => write!(f, "internal error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PasskeyError {}
+
+impl From<sqlx::Error> for PasskeyError {
+    fn from(e: sqlx::Error) -> Self {
+        PasskeyError::Db(e)
+    }
+}
+
+impl From<RedisError> for PasskeyError {
+    fn from(e: RedisError) -> Self {
+        PasskeyError::Redis(e)
+    }
+}
+
+impl From<WebauthnError> for PasskeyError {
+    fn from(e: WebauthnError) -> Self {
+        PasskeyError::Verification(e)
+    }
+}
+
+impl From<serde_json::Error> for PasskeyError {
+    fn from(e: serde_json::Error) -> Self {
+        PasskeyError::Internal(e.into())
+    }
+}
+
+impl From<AuthError> for PasskeyError {
+    fn from(e: AuthError) -> Self {
+        match e {
+            AuthError::Db(e) => PasskeyError::Db(e),
+            AuthError::AccountDisabled => PasskeyError::AccountDisabled,
+            other => PasskeyError::Internal(anyhow::anyhow!(other.to_string())),
+        }
+    }
+}
+
+/// Pending login ceremony kept in Redis between start and finish.
+#[derive(Serialize, Deserialize)]
+struct PendingLogin {
+    user_id: Uuid,
+    state: PasskeyAuthentication,
+}
+
+pub fn build_webauthn(cfg: &Config) -> anyhow::Result<Webauthn> {
+    let origin = Url::parse(&cfg.webauthn_rp_origin)
+        .map_err(|e| anyhow::anyhow!("invalid WEBAUTHN_RP_ORIGIN: {}", e))?;
+    let webauthn = WebauthnBuilder::new(&cfg.webauthn_rp_id, &origin)?
+        .rp_name("Tiny Note")
+        .build()?;
+    Ok(webauthn)
+}
+
+fn credential_key(passkey: &Passkey) -> String {
+    URL_SAFE_NO_PAD.encode(passkey.cred_id().as_ref())
+}
+
+async fn load_passkeys(
+    state: &AppState,
+    user_id: Uuid,
+) -> Result<Vec<(Uuid, Passkey)>, PasskeyError> {
+    let rows: Vec<(Uuid, String)> =
+        sqlx::query_as("SELECT id, passkey FROM passkeys WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(&state.db)
+            .await?;
+    rows.into_iter()
+        .map(|(id, json)| Ok((id, serde_json::from_str(&json)?)))
+        .collect()
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn start_registration(
+    state: &AppState,
+    user_id: Uuid,
+) -> Result<CreationChallengeResponse, PasskeyError> {
+    let (username, email): (String, String) =
+        sqlx::query_as("SELECT username, email FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(&state.db)
+            .await?;
+    let existing = load_passkeys(state, user_id).await?;
+    let exclude = existing
+        .iter()
+        .map(|(_, pk)| pk.cred_id().clone())
+        .collect::<Vec<_>>();
+
+    let (challenge, registration) = state.webauthn.start_passkey_registration(
+        user_id,
+        &email,
+        &username,
+        (!exclude.is_empty()).then_some(exclude),
+    )?;
+    store_json(
+        &state.redis,
+        &format!("webauthn_reg:{}", user_id),
+        &registration,
+        CEREMONY_TTL_SECONDS,
+    )
+    .await?;
+    Ok(challenge)
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn finish_registration(
+    state: &AppState,
+    user_id: Uuid,
+    req: FinishPasskeyRegistrationRequest,
+    client: &ClientInfo,
+) -> Result<PasskeyInfo, PasskeyError> {
+    let registration: PasskeyRegistration =
+        take_json(&state.redis, &format!("webauthn_reg:{}", user_id))
+            .await?
+            .ok_or(PasskeyError::ChallengeExpired)?;
+    let passkey = state
+        .webauthn
+        .finish_passkey_registration(&req.credential, &registration)?;
+
+    let credential_id = credential_key(&passkey);
+    let taken: Option<(i64,)> =
+        sqlx::query_as("SELECT 1 FROM passkeys WHERE credential_id = ? LIMIT 1")
+            .bind(&credential_id)
+            .fetch_optional(&state.db)
+            .await?;
+    if taken.is_some() {
+        return Err(PasskeyError::AlreadyRegistered);
+    }
+
+    let id = Uuid::new_v4();
+    let name = req
+        .name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| "Passkey".to_string());
+    sqlx::query("INSERT INTO passkeys (id, user_id, credential_id, name, passkey, created_at) VALUES (?, ?, ?, ?, ?, UTC_TIMESTAMP())")
+        .bind(id)
+        .bind(user_id)
+        .bind(&credential_id)
+        .bind(&name)
+        .bind(serde_json::to_string(&passkey)?)
+        .execute(&state.db)
+        .await?;
+
+    let details = serde_json::json!({ "passkey_id": id, "name": name });
+    audit_service::record(
+        state,
+        Some(user_id),
+        AuditEventType::PasskeyAdd,
+        true,
+        client,
+        details,
+    )
+    .await;
+    tracing::info!(user_id = %user_id, passkey_id = %id, "Passkey registered");
+    let info = sqlx::query_as::<_, PasskeyInfo>(
+        "SELECT id, name, created_at, last_used_at FROM passkeys WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_one(&state.db)
+    .await?;
+    Ok(info)
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn list_passkeys(
+    state: &AppState,
+    user_id: Uuid,
+) -> Result<Vec<PasskeyInfo>, PasskeyError> {
+    let passkeys = sqlx::query_as::<_, PasskeyInfo>(
+        "SELECT id, name, created_at, last_used_at FROM passkeys WHERE user_id = ? ORDER BY created_at DESC",
+    )
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await?;
+    Ok(passkeys)
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn delete_passkey(
+    state: &AppState,
+    user_id: Uuid,
+    passkey_id: Uuid,
+    client: &ClientInfo,
+) -> Result<(), PasskeyError> {
+    let res = sqlx::query("DELETE FROM passkeys WHERE id = ? AND user_id = ?")
+        .bind(passkey_id)
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+    if res.rows_affected() == 0 {
+        return Err(PasskeyError::NotFound);
+    }
+    let details = serde_json::json!({ "passkey_id": passkey_id });
+    audit_service::record(
+        state,
+        Some(user_id),
+        AuditEventType::PasskeyRemove,
+        true,
+        client,
+        details,
+    )
+    .await;
+    tracing::info!(user_id = %user_id, passkey_id = %passkey_id, "Passkey removed");
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn start_login(
+    state: &AppState,
+    req: StartPasskeyLoginRequest,
+) -> Result<StartPasskeyLoginResponse, PasskeyError> {
+    let user_id = find_user_by_identifier(state, &req.identifier)
+        .await?
+        .map(|u| u.id)
+        .ok_or(PasskeyError::NoPasskeys)?;
+
+    let passkeys: Vec<Passkey> = load_passkeys(state, user_id)
+        .await?
+        .into_iter()
+        .map(|(_, pk)| pk)
+        .collect();
+    if passkeys.is_empty() {
+        return Err(PasskeyError::NoPasskeys);
+    }
+
+    let (options, auth_state) = state.webauthn.start_passkey_authentication(&passkeys)?;
+    let challenge_id = Uuid::new_v4().simple().to_string();
+    let pending = PendingLogin {
+        user_id,
+        state: auth_state,
+    };
+    store_json(
+        &state.redis,
+        &format!("webauthn_auth:{}", challenge_id),
+        &pending,
+        CEREMONY_TTL_SECONDS,
+    )
+    .await?;
+    Ok(StartPasskeyLoginResponse {
+        challenge_id,
+        options,
+    })
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn finish_login(
+    state: &AppState,
+    req: FinishPasskeyLoginRequest,
+    client: &ClientInfo,
+) -> Result<LoginResponse, PasskeyError> {
+    let pending: PendingLogin =
+        take_json(&state.redis, &format!("webauthn_auth:{}", req.challenge_id))
+            .await?
+            .ok_or(PasskeyError::ChallengeExpired)?;
+    let result = match state
+        .webauthn
+        .finish_passkey_authentication(&req.credential, &pending.state)
+    {
+        Ok(r) => r,
+        Err(e) => {
+            record_login_failure(
+                state,
+                Some(pending.user_id),
+                client,
+                LoginMethod::Passkey,
+                "verification_failed",
+            )
+            .await;
+            return Err(e.into());
+        }
+    };
+
+    // Persist the updated signature counter / backup state of the credential that was used
+    for (id, mut passkey) in load_passkeys(state, pending.user_id).await? {
+        if passkey.cred_id() != result.cred_id() {
+            continue;
+        }
+        let updated = passkey.update_credential(&result).unwrap_or(false);
+        let mut q = String::from("UPDATE passkeys SET last_used_at = UTC_TIMESTAMP()");
+        if updated {
+            q.push_str(", passkey = ?");
+        }
+        q.push_str(" WHERE id = ?");
+        let mut query = sqlx::query(&q);
+        if updated {
+            query = query.bind(serde_json::to_string(&passkey)?);
+        }
+        query.bind(id).execute(&state.db).await?;
+    }
+
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, username, email, password_hash, CAST(created_at AS DATETIME) AS created_at FROM users WHERE id = ?")
+        .bind(pending.user_id)
+        .fetch_one(&state.db)
+        .await?;
+    tracing::info!(user_id = %user.id, "Passkey assertion verified");
+    Ok(issue_login_response(state, &user, client, LoginMethod::Passkey).await?)
+}
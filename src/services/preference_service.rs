@@ -0,0 +1,73 @@
+use crate::{
+    models::preferences::{Preferences, UpdatePreferencesRequest},
+    utils::time_zone,
+    AppState,
+};
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum PreferenceError {
+    NotFound,
+    Db(sqlx::Error),
+}
+
+impl std::fmt::Display for PreferenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PreferenceError::NotFound => write!(f, "user not found"),
+            PreferenceError::Db(e) => write!(f, "db error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PreferenceError {}
+
+impl From<sqlx::Error> for PreferenceError {
+    fn from(e: sqlx::Error) -> Self {
+        PreferenceError::Db(e)
+    }
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn get_preferences(
+    state: &AppState,
+    user_id: Uuid,
+) -> Result<Preferences, PreferenceError> {
+    let row: Option<(Option<String>,)> = sqlx::query_as("SELECT time_zone FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await?;
+    let (time_zone,) = row.ok_or(PreferenceError::NotFound)?;
+    Ok(Preferences {
+        // Stored names that no longer parse are treated as unset, as in `require_auth`
+        time_zone: time_zone.filter(|tz| time_zone::parse(tz).is_some()),
+        default_time_zone: state.config.default_time_zone.name().to_string(),
+    })
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn update_preferences(
+    state: &AppState,
+    user_id: Uuid,
+    req: UpdatePreferencesRequest,
+) -> Result<Preferences, PreferenceError> {
+    // Store the canonical name, e.g. `europe/berlin` -> `Europe/Berlin`
+    let time_zone = req
+        .time_zone
+        .as_deref()
+        .and_then(time_zone::parse)
+        .map(|tz| tz.name().to_string());
+    let res = sqlx::query("UPDATE users SET time_zone = ? WHERE id = ?")
+        .bind(&time_zone)
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+    if res.rows_affected() == 0 {
+        // MySQL reports unchanged rows as unaffected; tell the two cases apart
+        return get_preferences(state, user_id).await;
+    }
+    Ok(Preferences {
+        time_zone,
+        default_time_zone: state.config.default_time_zone.name().to_string(),
+    })
+}
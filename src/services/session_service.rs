@@ -0,0 +1,202 @@
+use crate::{
+    app_middleware::client_info::ClientInfo,
+    db::redis::{self, RedisError},
+    models::{audit::AuditEventType, session::SessionInfo},
+    services::audit_service,
+    utils::jwt::Claims,
+    AppState,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum SessionError {
+    NotFound,
+    Redis(RedisError),
+}
+
+impl std::fmt::Display for SessionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionError::NotFound => write!(f, "session not found"),
+            SessionError::Redis(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<RedisError> for SessionError {
+    fn from(e: RedisError) -> Self {
+        SessionError::Redis(e)
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Records the device behind a newly issued session token.
+#[tracing::instrument(skip_all)]
+pub async fn record_session(
+    state: &AppState,
+    claims: &Claims,
+    client: &ClientInfo,
+) -> Result<(), RedisError> {
+    let now = now();
+    let ttl = claims.exp as i64 - now;
+    if ttl <= 0 {
+        return Ok(());
+    }
+    let fields = [
+        ("user_id", claims.sub.to_string()),
+        (
+            "device_name",
+            client.device_name.clone().unwrap_or_default(),
+        ),
+        ("user_agent", client.user_agent.clone().unwrap_or_default()),
+        ("ip", client.ip.clone().unwrap_or_default()),
+        ("created_at", now.to_string()),
+        ("last_seen_at", now.to_string()),
+        ("expires_at", claims.exp.to_string()),
+    ];
+    redis::store_session(&state.redis, claims.sub, &claims.jti, &fields, ttl).await
+}
+
+/// Marks a session as seen; called by `require_auth` on every authenticated request.
+#[tracing::instrument(skip_all)]
+pub async fn touch_session(state: &AppState, jti: &str) -> Result<(), RedisError> {
+    redis::touch_session(&state.redis, jti, now()).await
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn list_sessions(
+    state: &AppState,
+    user_id: Uuid,
+    current_jti: &str,
+) -> Result<Vec<SessionInfo>, SessionError> {
+    let mut sessions: Vec<SessionInfo> = redis::list_sessions(&state.redis, user_id)
+        .await?
+        .into_iter()
+        .map(|(jti, fields)| {
+            let current = jti == current_jti;
+            SessionInfo::from_fields(jti, &fields, current)
+        })
+        .collect();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.last_seen_at));
+    Ok(sessions)
+}
+
+/// Blacklists the session token for its remaining lifetime and forgets the device.
+/// The revocation itself survives a Redis outage; the device entry then
+/// lingers until its TTL.
+async fn revoke(
+    state: &AppState,
+    user_id: Uuid,
+    jti: &str,
+    expires_at: Option<i64>,
+) -> Result<(), RedisError> {
+    if let Some(expires_at) = expires_at {
+        state
+            .revocations
+            .revoke(&state.redis, jti, expires_at)
+            .await;
+    }
+    if let Err(e) = redis::delete_session(&state.redis, user_id, jti).await {
+        tracing::warn!(error = %e, jti = %jti, "failed to remove session entry");
+    }
+    Ok(())
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn revoke_session(
+    state: &AppState,
+    user_id: Uuid,
+    jti: &str,
+    client: &ClientInfo,
+) -> Result<(), SessionError> {
+    let fields = redis::get_session(&state.redis, jti).await?;
+    // Sessions of other users look exactly like missing ones
+    if fields.get("user_id").map(String::as_str) != Some(user_id.to_string().as_str()) {
+        return Err(SessionError::NotFound);
+    }
+    let expires_at = fields.get("expires_at").and_then(|v| v.parse().ok());
+    revoke(state, user_id, jti, expires_at).await?;
+    let details = json!({ "session_id": jti });
+    audit_service::record(
+        state,
+        Some(user_id),
+        AuditEventType::SessionRevoke,
+        true,
+        client,
+        details,
+    )
+    .await;
+    tracing::info!(user_id = %user_id, jti = %jti, "Session revoked");
+    Ok(())
+}
+
+async fn revoke_sessions(
+    state: &AppState,
+    user_id: Uuid,
+    keep: Option<&str>,
+) -> Result<usize, SessionError> {
+    let mut revoked = 0;
+    for (jti, fields) in redis::list_sessions(&state.redis, user_id).await? {
+        if Some(jti.as_str()) == keep {
+            continue;
+        }
+        let expires_at = fields.get("expires_at").and_then(|v| v.parse().ok());
+        revoke(state, user_id, &jti, expires_at).await?;
+        revoked += 1;
+    }
+    Ok(revoked)
+}
+
+/// Signs out every device except the one making the request; returns how many were revoked.
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn revoke_other_sessions(
+    state: &AppState,
+    user_id: Uuid,
+    current_jti: &str,
+) -> Result<usize, SessionError> {
+    let revoked = revoke_sessions(state, user_id, Some(current_jti)).await?;
+    tracing::info!(user_id = %user_id, revoked, "Other sessions revoked");
+    Ok(revoked)
+}
+
+/// "Sign out everywhere else" requested by the user.
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn sign_out_other_devices(
+    state: &AppState,
+    user_id: Uuid,
+    current_jti: &str,
+    client: &ClientInfo,
+) -> Result<usize, SessionError> {
+    let revoked = revoke_other_sessions(state, user_id, current_jti).await?;
+    let details = json!({ "sessions_revoked": revoked });
+    audit_service::record(
+        state,
+        Some(user_id),
+        AuditEventType::SessionRevoke,
+        true,
+        client,
+        details,
+    )
+    .await;
+    Ok(revoked)
+}
+
+/// Signs out every device of a user, e.g. when an administrator disables the account.
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn revoke_all_sessions(state: &AppState, user_id: Uuid) -> Result<usize, SessionError> {
+    let revoked = revoke_sessions(state, user_id, None).await?;
+    tracing::info!(user_id = %user_id, revoked, "All sessions revoked");
+    Ok(revoked)
+}
+
+/// Ends the current session on logout.
+#[tracing::instrument(skip_all)]
+pub async fn end_session(state: &AppState, claims: &Claims) -> Result<(), RedisError> {
+    revoke(state, claims.sub, &claims.jti, Some(claims.exp as i64)).await
+}
@@ -0,0 +1,327 @@
+use crate::{
+    app_middleware::client_info::ClientInfo,
+    db::mysql::Traced,
+    models::{
+        audit::AuditEventType,
+        totp::{RecoveryCodesResponse, TotpCodeRequest, TotpEnrollResponse},
+    },
+    services::audit_service,
+    AppState,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use totp_rs::{Builder, Secret, Totp};
+use uuid::Uuid;
+
+const ISSUER: &str = "Tiny Note";
+const RECOVERY_CODE_COUNT: usize = 10;
+
+#[derive(Debug)]
+pub enum TotpError {
+    NotEnrolled,
+    AlreadyEnabled,
+    InvalidCode,
+    Db(sqlx::Error),
+    Internal(anyhow::Error),
+}
+
+impl std::fmt::Display for TotpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TotpError::NotEnrolled => write!(f, "two-factor authentication is not set up"),
+            TotpError::AlreadyEnabled => write!(f, "two-factor authentication is already enabled"),
+            TotpError::InvalidCode => write!(f, "invalid two-factor code"),
+            TotpError::Db(e) => write!(f, "db error: {}", e),
+            TotpError::Internal(e) => write!(f, "internal error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TotpError {}
+
+impl From<sqlx::Error> for TotpError {
+    fn from(e: sqlx::Error) -> Self {
+        TotpError::Db(e)
+    }
+}
+
+impl From<anyhow::Error> for TotpError {
+    fn from(e: anyhow::Error) -> Self {
+        TotpError::Internal(e)
+    }
+}
+
+struct TotpState {
+    email: String,
+    secret: Option<String>,
+    enabled: bool,
+}
+
+async fn load_state(state: &AppState, user_id: Uuid) -> Result<TotpState, TotpError> {
+    let row: (String, Option<String>, bool) =
+        sqlx::query_as("SELECT email, totp_secret, totp_enabled FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(&state.db)
+            .await?;
+    Ok(TotpState {
+        email: row.0,
+        secret: row.1,
+        enabled: row.2,
+    })
+}
+
+fn build_totp(secret: Secret, email: &str) -> anyhow::Result<Totp> {
+    Builder::new()
+        .with_secret(secret)
+        .with_issuer(Some(ISSUER))
+        // ':' separates issuer and account in the otpauth label
+        .with_account_name(email.replace(':', ""))
+        .build()
+        .map_err(|e| anyhow::anyhow!("failed to build TOTP: {}", e))
+}
+
+fn parse_secret(secret: &str, email: &str) -> anyhow::Result<Totp> {
+    let secret = Secret::try_from_base32(secret)
+        .map_err(|e| anyhow::anyhow!("invalid stored TOTP secret: {}", e))?;
+    build_totp(secret, email)
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    format!("{:x}", Sha256::digest(normalized.as_bytes()))
+}
+
+fn generate_recovery_code() -> String {
+    let raw: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(10)
+        .map(|c| (c as char).to_ascii_lowercase())
+        .collect();
+    format!("{}-{}", &raw[..5], &raw[5..])
+}
+
+/// Replaces all recovery codes of the user and returns the new plaintext codes.
+async fn replace_recovery_codes(state: &AppState, user_id: Uuid) -> Result<Vec<String>, TotpError> {
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+
+    let mut tx = state.db.begin().await?;
+    sqlx::query("DELETE FROM totp_recovery_codes WHERE user_id = ?")
+        .bind(user_id)
+        .execute(Traced(&mut *tx))
+        .await?;
+    for code in &codes {
+        sqlx::query("INSERT INTO totp_recovery_codes (id, user_id, code_hash, created_at) VALUES (?, ?, ?, UTC_TIMESTAMP())")
+            .bind(Uuid::new_v4())
+            .bind(user_id)
+            .bind(hash_recovery_code(code))
+            .execute(Traced(&mut *tx))
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(codes)
+}
+
+/// Accepts a TOTP code at most once per time step.
+async fn check_totp(
+    state: &AppState,
+    user_id: Uuid,
+    totp: &Totp,
+    code: &str,
+) -> Result<bool, TotpError> {
+    let step = match totp.check_current(code.trim()) {
+        Some(step) => step,
+        None => return Ok(false),
+    };
+    let res = sqlx::query("UPDATE users SET totp_last_step = ? WHERE id = ? AND (totp_last_step IS NULL OR totp_last_step < ?)")
+        .bind(step as i64)
+        .bind(user_id)
+        .bind(step as i64)
+        .execute(&state.db)
+        .await?;
+    Ok(res.rows_affected() == 1)
+}
+
+async fn consume_recovery_code(
+    state: &AppState,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, TotpError> {
+    let res = sqlx::query("UPDATE totp_recovery_codes SET used_at = UTC_TIMESTAMP() WHERE user_id = ? AND code_hash = ? AND used_at IS NULL")
+        .bind(user_id)
+        .bind(hash_recovery_code(code))
+        .execute(&state.db)
+        .await?;
+    Ok(res.rows_affected() == 1)
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn is_enabled(state: &AppState, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let enabled: Option<(bool,)> = sqlx::query_as("SELECT totp_enabled FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await?;
+    Ok(enabled.map(|(e,)| e).unwrap_or(false))
+}
+
+/// Verifies a second factor for an enabled account: a current TOTP code or an unused recovery code.
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn verify_code(state: &AppState, user_id: Uuid, code: &str) -> Result<bool, TotpError> {
+    let current = load_state(state, user_id).await?;
+    let secret = match (current.enabled, current.secret) {
+        (true, Some(secret)) => secret,
+        _ => return Err(TotpError::NotEnrolled),
+    };
+    let totp = parse_secret(&secret, &current.email)?;
+    if check_totp(state, user_id, &totp, code).await? {
+        return Ok(true);
+    }
+    consume_recovery_code(state, user_id, code).await
+}
+
+/// A wrong code on a 2FA change may mean someone else holds the session.
+async fn record_invalid_code(
+    state: &AppState,
+    user_id: Uuid,
+    event: AuditEventType,
+    client: &ClientInfo,
+) {
+    let details = json!({ "reason": "invalid_code" });
+    audit_service::record(state, Some(user_id), event, false, client, details).await;
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn enroll(state: &AppState, user_id: Uuid) -> Result<TotpEnrollResponse, TotpError> {
+    let current = load_state(state, user_id).await?;
+    if current.enabled {
+        return Err(TotpError::AlreadyEnabled);
+    }
+
+    let totp = build_totp(Secret::generate(), &current.email)?;
+    let secret = totp.secret().to_base32();
+    let otpauth_url = totp
+        .to_url()
+        .map_err(|e| anyhow::anyhow!("failed to build otpauth url: {}", e))?;
+
+    // Pending until confirmed with a valid code
+    sqlx::query(
+        "UPDATE users SET totp_secret = ?, totp_enabled = 0, totp_last_step = NULL WHERE id = ?",
+    )
+    .bind(&secret)
+    .bind(user_id)
+    .execute(&state.db)
+    .await?;
+
+    tracing::info!(user_id = %user_id, "TOTP enrollment started");
+    Ok(TotpEnrollResponse {
+        secret,
+        otpauth_url,
+    })
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn confirm(
+    state: &AppState,
+    user_id: Uuid,
+    req: TotpCodeRequest,
+    client: &ClientInfo,
+) -> Result<RecoveryCodesResponse, TotpError> {
+    let current = load_state(state, user_id).await?;
+    if current.enabled {
+        return Err(TotpError::AlreadyEnabled);
+    }
+    let secret = current.secret.ok_or(TotpError::NotEnrolled)?;
+    let totp = parse_secret(&secret, &current.email)?;
+    if !check_totp(state, user_id, &totp, &req.code).await? {
+        record_invalid_code(state, user_id, AuditEventType::TotpEnable, client).await;
+        return Err(TotpError::InvalidCode);
+    }
+
+    sqlx::query("UPDATE users SET totp_enabled = 1 WHERE id = ?")
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+    let recovery_codes = replace_recovery_codes(state, user_id).await?;
+    audit_service::record(
+        state,
+        Some(user_id),
+        AuditEventType::TotpEnable,
+        true,
+        client,
+        serde_json::Value::Null,
+    )
+    .await;
+
+    tracing::info!(user_id = %user_id, "TOTP enabled");
+    Ok(RecoveryCodesResponse { recovery_codes })
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn regenerate_recovery_codes(
+    state: &AppState,
+    user_id: Uuid,
+    req: TotpCodeRequest,
+    client: &ClientInfo,
+) -> Result<RecoveryCodesResponse, TotpError> {
+    let event = AuditEventType::RecoveryCodesRegenerate;
+    if !verify_code(state, user_id, &req.code).await? {
+        record_invalid_code(state, user_id, event, client).await;
+        return Err(TotpError::InvalidCode);
+    }
+    let recovery_codes = replace_recovery_codes(state, user_id).await?;
+    audit_service::record(
+        state,
+        Some(user_id),
+        event,
+        true,
+        client,
+        serde_json::Value::Null,
+    )
+    .await;
+    tracing::info!(user_id = %user_id, "TOTP recovery codes regenerated");
+    Ok(RecoveryCodesResponse { recovery_codes })
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
+pub async fn disable(
+    state: &AppState,
+    user_id: Uuid,
+    req: TotpCodeRequest,
+    client: &ClientInfo,
+) -> Result<(), TotpError> {
+    if !verify_code(state, user_id, &req.code).await? {
+        record_invalid_code(state, user_id, AuditEventType::TotpDisable, client).await;
+        return Err(TotpError::InvalidCode);
+    }
+
+    let mut tx = state.db.begin().await?;
+    sqlx::query(
+        "UPDATE users SET totp_secret = NULL, totp_enabled = 0, totp_last_step = NULL WHERE id = ?",
+    )
+    .bind(user_id)
+    .execute(Traced(&mut *tx))
+    .await?;
+    sqlx::query("DELETE FROM totp_recovery_codes WHERE user_id = ?")
+        .bind(user_id)
+        .execute(Traced(&mut *tx))
+        .await?;
+    tx.commit().await?;
+    audit_service::record(
+        state,
+        Some(user_id),
+        AuditEventType::TotpDisable,
+        true,
+        client,
+        serde_json::Value::Null,
+    )
+    .await;
+
+    tracing::info!(user_id = %user_id, "TOTP disabled");
+    Ok(())
+}
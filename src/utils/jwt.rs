@@ -1,6 +1,18 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use rsa::{
+    pkcs1::{DecodeRsaPrivateKey, EncodeRsaPrivateKey},
+    traits::PublicKeyParts,
+    RsaPrivateKey,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,20 +22,207 @@ pub struct Claims {
     pub jti: String,
 }
 
-pub fn generate_token(user_id: Uuid, secret: &str, ttl_minutes: i64) -> anyhow::Result<(String, Claims)> {
+/// An asymmetric signing key identified by `kid`.
+pub struct JwtKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    /// Public part in JWK form, as published on the JWKS endpoint
+    jwk: serde_json::Value,
+}
+
+impl JwtKey {
+    /// Generates a new key pair and returns it with its private key DER for storage.
+    pub fn generate(algorithm: Algorithm) -> anyhow::Result<(JwtKey, Vec<u8>)> {
+        let der = match algorithm {
+            Algorithm::EdDSA => Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new())
+                .map_err(|_| anyhow::anyhow!("failed to generate Ed25519 key"))?
+                .as_ref()
+                .to_vec(),
+            Algorithm::RS256 => RsaPrivateKey::new(&mut rand::rngs::OsRng, 2048)?
+                .to_pkcs1_der()?
+                .as_bytes()
+                .to_vec(),
+            other => anyhow::bail!("unsupported signing algorithm: {:?}", other),
+        };
+        let kid = Uuid::new_v4().simple().to_string();
+        let key = JwtKey::from_private_der(kid, algorithm, &der)?;
+        Ok((key, der))
+    }
+
+    /// Loads a key from its private DER (PKCS#8 for EdDSA, PKCS#1 for RS256).
+    pub fn from_private_der(
+        kid: String,
+        algorithm: Algorithm,
+        der: &[u8],
+    ) -> anyhow::Result<JwtKey> {
+        let (encoding, decoding, public_jwk) = match algorithm {
+            Algorithm::EdDSA => {
+                let pair = Ed25519KeyPair::from_pkcs8(der)
+                    .map_err(|e| anyhow::anyhow!("invalid Ed25519 key {}: {}", kid, e))?;
+                let x = URL_SAFE_NO_PAD.encode(pair.public_key().as_ref());
+                (
+                    EncodingKey::from_ed_der(der),
+                    DecodingKey::from_ed_components(&x)?,
+                    json!({ "kty": "OKP", "crv": "Ed25519", "x": x }),
+                )
+            }
+            Algorithm::RS256 => {
+                let private = RsaPrivateKey::from_pkcs1_der(der)?;
+                let n = URL_SAFE_NO_PAD.encode(private.n().to_bytes_be());
+                let e = URL_SAFE_NO_PAD.encode(private.e().to_bytes_be());
+                (
+                    EncodingKey::from_rsa_der(der),
+                    DecodingKey::from_rsa_components(&n, &e)?,
+                    json!({ "kty": "RSA", "n": n, "e": e }),
+                )
+            }
+            other => anyhow::bail!("unsupported signing algorithm: {:?}", other),
+        };
+
+        let mut jwk = public_jwk;
+        jwk["kid"] = json!(kid);
+        jwk["alg"] = json!(algorithm_name(algorithm));
+        jwk["use"] = json!("sig");
+        Ok(JwtKey {
+            kid,
+            algorithm,
+            encoding,
+            decoding,
+            jwk,
+        })
+    }
+}
+
+pub fn algorithm_name(algorithm: Algorithm) -> &'static str {
+    match algorithm {
+        Algorithm::EdDSA => "EdDSA",
+        Algorithm::RS256 => "RS256",
+        Algorithm::HS256 => "HS256",
+        _ => "unsupported",
+    }
+}
+
+#[derive(Default)]
+struct KeySet {
+    signing: Option<Arc<JwtKey>>,
+    verification: HashMap<String, Arc<JwtKey>>,
+}
+
+/// Signing and verification keys shared by all requests.
+///
+/// With an HMAC secret (`JWT_ALGORITHM=HS256`) tokens are signed without `kid`
+/// exactly as before; otherwise the key set is filled from the `jwt_keys`
+/// table and swapped atomically on rotation.
+#[derive(Clone)]
+pub struct JwtKeys {
+    hmac_secret: Option<Arc<String>>,
+    keys: Arc<RwLock<KeySet>>,
+}
+
+impl JwtKeys {
+    pub fn hmac(secret: String) -> Self {
+        JwtKeys {
+            hmac_secret: Some(Arc::new(secret)),
+            keys: Arc::default(),
+        }
+    }
+
+    pub fn asymmetric() -> Self {
+        JwtKeys {
+            hmac_secret: None,
+            keys: Arc::default(),
+        }
+    }
+
+    pub fn is_asymmetric(&self) -> bool {
+        self.hmac_secret.is_none()
+    }
+
+    /// Replaces the key set; `signing` must also be part of `verification`.
+    pub fn replace(&self, signing: Option<JwtKey>, verification: Vec<JwtKey>) {
+        let mut set = KeySet::default();
+        for key in verification {
+            set.verification.insert(key.kid.clone(), Arc::new(key));
+        }
+        if let Some(key) = signing {
+            let key = Arc::new(key);
+            set.verification.insert(key.kid.clone(), key.clone());
+            set.signing = Some(key);
+        }
+        *self.keys.write().unwrap() = set;
+    }
+
+    /// Public verification keys as a JWK Set document.
+    pub fn jwks(&self) -> serde_json::Value {
+        let set = self.keys.read().unwrap();
+        let mut keys: Vec<&serde_json::Value> = set.verification.values().map(|k| &k.jwk).collect();
+        keys.sort_by(|a, b| a["kid"].as_str().cmp(&b["kid"].as_str()));
+        json!({ "keys": keys })
+    }
+}
+
+pub fn generate_token(
+    user_id: Uuid,
+    keys: &JwtKeys,
+    ttl_minutes: i64,
+) -> anyhow::Result<(String, Claims)> {
     let exp = Utc::now() + Duration::minutes(ttl_minutes);
     let claims = Claims {
         sub: user_id,
         exp: exp.timestamp() as usize,
         jti: Uuid::new_v4().to_string(),
     };
-    let token = encode(&Header::new(Algorithm::HS256), &claims, &EncodingKey::from_secret(secret.as_bytes()))?;
+    let token = match keys.hmac_secret.as_deref() {
+        Some(secret) => encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )?,
+        None => {
+            let key = keys
+                .keys
+                .read()
+                .unwrap()
+                .signing
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("no JWT signing key loaded"))?;
+            let mut header = Header::new(key.algorithm);
+            header.kid = Some(key.kid.clone());
+            encode(&header, &claims, &key.encoding)?
+        }
+    };
     Ok((token, claims))
 }
 
-pub fn validate_token(token: &str, secret: &str) -> anyhow::Result<Claims> {
-    let mut validation = Validation::new(Algorithm::HS256);
+pub fn validate_token(token: &str, keys: &JwtKeys) -> anyhow::Result<Claims> {
+    if let Some(secret) = keys.hmac_secret.as_deref() {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.validate_exp = true;
+        let data = decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(secret.as_bytes()),
+            &validation,
+        )?;
+        return Ok(data.claims);
+    }
+
+    let header = decode_header(token)?;
+    let kid = header
+        .kid
+        .ok_or_else(|| anyhow::anyhow!("token has no kid"))?;
+    let key = keys
+        .keys
+        .read()
+        .unwrap()
+        .verification
+        .get(&kid)
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("unknown signing key: {}", kid))?;
+    // The algorithm comes from our key, never from the token header
+    let mut validation = Validation::new(key.algorithm);
     validation.validate_exp = true;
-    let data = decode::<Claims>(token, &DecodingKey::from_secret(secret.as_bytes()), &validation)?;
+    let data = decode::<Claims>(token, &key.decoding, &validation)?;
     Ok(data.claims)
-}
\ No newline at end of file
+}
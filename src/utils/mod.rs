@@ -1,2 +1,9 @@
 pub mod jwt;
-pub mod password;
\ No newline at end of file
+pub mod metrics;
+pub mod password;
+pub mod password_policy;
+pub mod session_cookie;
+pub mod shutdown;
+pub mod telemetry;
+pub mod time_zone;
+pub mod tls;
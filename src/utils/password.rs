@@ -1,18 +1,187 @@
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use argon2::password_hash::{rand_core::OsRng, SaltString};
+use argon2::{
+    Algorithm, Argon2, KeyId, Params, ParamsBuilder, PasswordHash, PasswordHasher,
+    PasswordVerifier, Version,
+};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
 
-pub fn hash_password(password: &str) -> anyhow::Result<String> {
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let hash = argon2.hash_password(password.as_bytes(), &salt)
-        .map_err(|e| anyhow::anyhow!("Password hashing failed: {}", e))?
-        .to_string();
-    Ok(hash)
+use crate::config::Config;
+
+/// Argon2id hashing with the configured cost parameters and optional pepper.
+///
+/// Hashes made with a pepper carry a `keyid` derived from it, so hashes made
+/// before a pepper was configured can still be verified (and get upgraded on
+/// the next login).
+#[derive(Clone)]
+pub struct PasswordHashing {
+    params: Params,
+    pepper: Option<Arc<Vec<u8>>>,
+}
+
+impl PasswordHashing {
+    pub fn from_config(cfg: &Config) -> anyhow::Result<Self> {
+        let pepper = cfg
+            .password_pepper
+            .as_ref()
+            .map(|p| Arc::new(p.as_bytes().to_vec()));
+        let mut builder = ParamsBuilder::new();
+        builder
+            .m_cost(cfg.argon2_memory_kib)
+            .t_cost(cfg.argon2_iterations)
+            .p_cost(cfg.argon2_parallelism);
+        if let Some(pepper) = &pepper {
+            builder.keyid(pepper_key_id(pepper)?);
+        }
+        let params = builder
+            .build()
+            .map_err(|e| anyhow::anyhow!("invalid argon2 parameters: {}", e))?;
+        Ok(PasswordHashing { params, pepper })
+    }
+
+    fn argon2(&self, params: Params, with_pepper: bool) -> anyhow::Result<Argon2<'_>> {
+        match (&self.pepper, with_pepper) {
+            (Some(pepper), true) => {
+                Argon2::new_with_secret(pepper, Algorithm::Argon2id, Version::V0x13, params)
+                    .map_err(|e| anyhow::anyhow!("invalid argon2 pepper: {}", e))
+            }
+            _ => Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params)),
+        }
+    }
+
+    pub fn hash(&self, password: &str) -> anyhow::Result<String> {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = self
+            .argon2(self.params.clone(), true)?
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| anyhow::anyhow!("Password hashing failed: {}", e))?
+            .to_string();
+        Ok(hash)
+    }
+
+    pub fn verify(&self, password: &str, password_hash: &str) -> anyhow::Result<bool> {
+        let parsed_hash = PasswordHash::new(password_hash)
+            .map_err(|e| anyhow::anyhow!("Invalid password hash: {}", e))?;
+        let params = Params::try_from(&parsed_hash)
+            .map_err(|e| anyhow::anyhow!("Invalid password hash: {}", e))?;
+        let peppered = !params.keyid().is_empty();
+        if peppered && params.keyid() != self.params.keyid() {
+            tracing::warn!("Password hash was made with a different pepper");
+            return Ok(false);
+        }
+        // Cost parameters are taken from the stored hash, not from our config
+        let argon2 = self.argon2(Params::default(), peppered)?;
+        Ok(argon2
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_ok())
+    }
+
+    /// True when a stored hash uses another algorithm, weaker/different cost
+    /// parameters or a different pepper than currently configured.
+    pub fn needs_rehash(&self, password_hash: &str) -> bool {
+        let parsed_hash = match PasswordHash::new(password_hash) {
+            Ok(h) => h,
+            Err(_) => return true,
+        };
+        if parsed_hash.algorithm != Algorithm::Argon2id.ident()
+            || parsed_hash.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+        match Params::try_from(&parsed_hash) {
+            Ok(p) => {
+                p.m_cost() != self.params.m_cost()
+                    || p.t_cost() != self.params.t_cost()
+                    || p.p_cost() != self.params.p_cost()
+                    || p.keyid() != self.params.keyid()
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+/// Short identifier of the pepper stored in the PHC string; not secret.
+fn pepper_key_id(pepper: &[u8]) -> anyhow::Result<KeyId> {
+    let digest = Sha256::digest(pepper);
+    KeyId::new(&digest[..4]).map_err(|e| anyhow::anyhow!("invalid argon2 key id: {}", e))
 }
 
-pub fn verify_password(password: &str, password_hash: &str) -> anyhow::Result<bool> {
-    let parsed_hash = PasswordHash::new(password_hash)
-        .map_err(|e| anyhow::anyhow!("Invalid password hash: {}", e))?;
-    let argon2 = Argon2::default();
-    Ok(argon2.verify_password(password.as_bytes(), &parsed_hash).is_ok())
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters so the tests run fast.
+    fn hashing(iterations: u32, pepper: Option<&str>) -> PasswordHashing {
+        let mut toml = format!(
+            "argon2_memory_kib = 1024\nargon2_iterations = {}\nargon2_parallelism = 1",
+            iterations
+        );
+        if let Some(pepper) = pepper {
+            toml.push_str(&format!("\npassword_pepper = \"{}\"", pepper));
+        }
+        PasswordHashing::from_config(&Config::for_tests(&toml)).unwrap()
+    }
+
+    #[test]
+    fn verifies_its_own_hashes() {
+        let hashing = hashing(1, None);
+        let hash = hashing.hash("correct horse").unwrap();
+        assert!(hashing.verify("correct horse", &hash).unwrap());
+        assert!(!hashing.verify("wrong horse", &hash).unwrap());
+        assert!(!hashing.needs_rehash(&hash));
+    }
+
+    #[test]
+    fn outdated_parameters_need_a_rehash() {
+        let old = hashing(1, None).hash("correct horse").unwrap();
+        let current = hashing(2, None);
+        // Verified with the stored parameters, then upgraded
+        assert!(current.verify("correct horse", &old).unwrap());
+        assert!(current.needs_rehash(&old));
+        assert!(!current.needs_rehash(&current.hash("correct horse").unwrap()));
+
+        let argon2i = Argon2::new(
+            Algorithm::Argon2i,
+            Version::V0x13,
+            Params::new(1024, 1, 1, None).unwrap(),
+        )
+        .hash_password(b"correct horse", &SaltString::generate(&mut OsRng))
+        .unwrap()
+        .to_string();
+        assert!(current.needs_rehash(&argon2i));
+        assert!(current.needs_rehash("not a phc string"));
+    }
+
+    #[test]
+    fn hashes_need_the_pepper_they_were_made_with() {
+        let peppered = hashing(1, Some("pepper-1"));
+        let hash = peppered.hash("correct horse").unwrap();
+        assert!(peppered.verify("correct horse", &hash).unwrap());
+        assert!(!peppered.needs_rehash(&hash));
+
+        // Another pepper, or none at all, can't verify it
+        let other = hashing(1, Some("pepper-2"));
+        assert!(!other.verify("correct horse", &hash).unwrap());
+        assert!(other.needs_rehash(&hash));
+        assert!(!hashing(1, None).verify("correct horse", &hash).unwrap());
+    }
+
+    #[test]
+    fn unknown_pepper_key_id_fails_verification() {
+        let peppered = hashing(1, Some("pepper-1"));
+        let hash = peppered.hash("correct horse").unwrap();
+        let parsed = PasswordHash::new(&hash).unwrap();
+        let keyid = parsed.params.get_str("keyid").unwrap().to_string();
+        let forged = hash.replace(&format!("keyid={}", keyid), "keyid=AAAAAA");
+        assert_ne!(forged, hash);
+        assert!(!peppered.verify("correct horse", &forged).unwrap());
+    }
+
+    #[test]
+    fn hashes_from_before_the_pepper_still_verify() {
+        let legacy = hashing(1, None).hash("correct horse").unwrap();
+        let peppered = hashing(1, Some("pepper-1"));
+        assert!(peppered.verify("correct horse", &legacy).unwrap());
+        assert!(peppered.needs_rehash(&legacy));
+    }
+}
@@ -0,0 +1,186 @@
+use sha1::{Digest, Sha1};
+use std::path::Path;
+use zxcvbn::zxcvbn;
+
+use crate::{config::Config, models::validation::FieldError};
+
+/// Upper bound that keeps hashing cost predictable.
+const MAX_PASSWORD_LENGTH: usize = 256;
+
+/// Checks a new password against the configured policy and returns every
+/// violation found; an empty list means the password is acceptable.
+///
+/// `user_inputs` (username, email, ...) are penalised by the strength estimate.
+pub async fn check_password(
+    cfg: &Config,
+    field: &str,
+    password: &str,
+    user_inputs: &[&str],
+) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let length = password.chars().count();
+    if length < cfg.password_min_length {
+        errors.push(FieldError::new(
+            field,
+            "too_short",
+            format!("must be at least {} characters", cfg.password_min_length),
+        ));
+    }
+    if length > MAX_PASSWORD_LENGTH {
+        errors.push(FieldError::new(
+            field,
+            "too_long",
+            format!("must be at most {} characters", MAX_PASSWORD_LENGTH),
+        ));
+        return errors;
+    }
+
+    if !password.is_empty() {
+        let entropy = zxcvbn(password, user_inputs);
+        if (entropy.score() as u8) < cfg.password_min_score {
+            let hint = entropy
+                .feedback()
+                .and_then(|f| {
+                    f.warning()
+                        .map(|w| w.to_string())
+                        .or_else(|| f.suggestions().first().map(|s| s.to_string()))
+                })
+                .unwrap_or_else(|| "add more words or characters".to_string());
+            errors.push(FieldError::new(
+                field,
+                "too_weak",
+                format!("is too easy to guess: {}", hint),
+            ));
+        }
+    }
+
+    if let Some(dir) = &cfg.breached_passwords_dir {
+        match is_breached(dir, password).await {
+            Ok(true) => errors.push(FieldError::new(
+                field,
+                "breached",
+                "appears in a known data breach, please choose another password",
+            )),
+            Ok(false) => {}
+            // The list is a best-effort extra check; don't block sign-ups on I/O problems
+            Err(e) => tracing::warn!(error = %e, "Breached password lookup failed"),
+        }
+    }
+    errors
+}
+
+/// Looks the password up in a local copy of a breached-password range list.
+///
+/// The directory holds one file per 5-hex-digit SHA-1 prefix (`<PREFIX>.txt`),
+/// each line being `<35-hex-digit suffix>:<count>`, the same layout as the
+/// Pwned Passwords range API, so only the matching range is read.
+async fn is_breached(dir: &Path, password: &str) -> std::io::Result<bool> {
+    let digest = format!("{:X}", Sha1::digest(password.as_bytes()));
+    let (prefix, suffix) = digest.split_at(5);
+    let contents = match tokio::fs::read_to_string(dir.join(format!("{}.txt", prefix))).await {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    Ok(contents.lines().any(|line| {
+        line.split(':')
+            .next()
+            .is_some_and(|s| s.trim().eq_ignore_ascii_case(suffix))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(errors: &[FieldError]) -> Vec<&str> {
+        errors.iter().map(|e| e.code.as_str()).collect()
+    }
+
+    /// A range directory holding `password` with the Pwned Passwords layout.
+    fn breached_dir(password: &str) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let digest = format!("{:X}", Sha1::digest(password.as_bytes()));
+        let (prefix, suffix) = digest.split_at(5);
+        let lines = format!("0000000000000000000000000000000000A:3\r\n{}:42\r\n", suffix);
+        std::fs::write(dir.path().join(format!("{}.txt", prefix)), lines).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn breached_password_is_rejected() {
+        let dir = breached_dir("Tr0ub4dor&3-breached");
+        let cfg = Config::for_tests(&format!(
+            "password_min_score = 0\nbreached_passwords_dir = {:?}",
+            dir.path().display().to_string()
+        ));
+        let errors = check_password(&cfg, "password", "Tr0ub4dor&3-breached", &[]).await;
+        assert_eq!(codes(&errors), ["breached"]);
+        assert_eq!(errors[0].field, "password");
+
+        // A password whose range has no file is not breached
+        assert!(!is_breached(dir.path(), "not in the list at all")
+            .await
+            .unwrap());
+        assert!(
+            check_password(&cfg, "password", "not in the list at all", &[])
+                .await
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn breached_lookup_matches_suffix_case_insensitively() {
+        let dir = tempfile::tempdir().unwrap();
+        let digest = format!("{:X}", Sha1::digest(b"lowercase-range"));
+        let (prefix, suffix) = digest.split_at(5);
+        std::fs::write(
+            dir.path().join(format!("{}.txt", prefix)),
+            format!("{}:1\n", suffix.to_lowercase()),
+        )
+        .unwrap();
+        assert!(is_breached(dir.path(), "lowercase-range").await.unwrap());
+        assert!(!is_breached(dir.path(), "lowercase-range!").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn minimum_score_is_inclusive() {
+        // zxcvbn rates this 2 of 4
+        let password = "kittenmittens";
+        assert_eq!(zxcvbn(password, &[]).score() as u8, 2);
+
+        let cfg = Config::for_tests("password_min_score = 2");
+        assert!(check_password(&cfg, "password", password, &[])
+            .await
+            .is_empty());
+
+        let cfg = Config::for_tests("password_min_score = 3");
+        let errors = check_password(&cfg, "password", password, &[]).await;
+        assert_eq!(codes(&errors), ["too_weak"]);
+    }
+
+    #[tokio::test]
+    async fn user_inputs_lower_the_score() {
+        let cfg = Config::for_tests("password_min_score = 3");
+        let password = "zebrastripes1987";
+        assert!(check_password(&cfg, "password", password, &[])
+            .await
+            .is_empty());
+        let errors = check_password(&cfg, "password", password, &["zebrastripes"]).await;
+        assert_eq!(codes(&errors), ["too_weak"]);
+    }
+
+    #[tokio::test]
+    async fn length_limits() {
+        let cfg = Config::for_tests("password_min_length = 12\npassword_min_score = 0");
+        assert_eq!(
+            codes(&check_password(&cfg, "password", "short", &[]).await),
+            ["too_short"]
+        );
+        let long = "x".repeat(MAX_PASSWORD_LENGTH + 1);
+        assert_eq!(
+            codes(&check_password(&cfg, "password", &long, &[]).await),
+            ["too_long"]
+        );
+    }
+}
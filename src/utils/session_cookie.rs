@@ -0,0 +1,110 @@
+use crate::{config::Config, models::user::LoginResponse};
+use rand::{distributions::Alphanumeric, Rng};
+use tower_cookies::{
+    cookie::{time::Duration, SameSite},
+    Cookie, Cookies,
+};
+
+/// HttpOnly cookie carrying the session JWT for browser clients.
+pub const SESSION_COOKIE: &str = "tn_session";
+/// Readable cookie holding the CSRF token; must be echoed in [`CSRF_HEADER`].
+pub const CSRF_COOKIE: &str = "tn_csrf";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+/// HttpOnly cookie holding the `state` of an OIDC login, so the callback is
+/// only accepted in the browser that started it.
+pub const OIDC_STATE_COOKIE: &str = "tn_oidc_state";
+
+fn build_cookie(
+    cfg: &Config,
+    name: &'static str,
+    value: String,
+    http_only: bool,
+    max_age: Duration,
+) -> Cookie<'static> {
+    Cookie::build((name, value))
+        .path("/")
+        .http_only(http_only)
+        .secure(cfg.session_cookie_secure)
+        .same_site(cfg.session_cookie_same_site)
+        .max_age(max_age)
+        .build()
+}
+
+/// Moves the token of a login response into the session cookie and issues a
+/// fresh CSRF token, returned in the body so cross-origin frontends can read it.
+pub fn set_session_cookies(
+    cookies: &Cookies,
+    cfg: &Config,
+    resp: &mut LoginResponse,
+    ttl_minutes: i64,
+) {
+    let token = match resp.token.take() {
+        Some(t) => t,
+        None => return,
+    };
+    let csrf: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+    let max_age = Duration::minutes(ttl_minutes);
+    cookies.add(build_cookie(cfg, SESSION_COOKIE, token, true, max_age));
+    cookies.add(build_cookie(cfg, CSRF_COOKIE, csrf.clone(), false, max_age));
+    resp.csrf_token = Some(csrf);
+}
+
+pub fn clear_session_cookies(cookies: &Cookies, cfg: &Config) {
+    for name in [SESSION_COOKIE, CSRF_COOKIE] {
+        cookies.add(build_cookie(
+            cfg,
+            name,
+            String::new(),
+            name == SESSION_COOKIE,
+            Duration::ZERO,
+        ));
+    }
+}
+
+/// `SameSite=Lax` regardless of `SESSION_COOKIE_SAME_SITE`: the callback is a
+/// top-level navigation from the identity provider, which `Strict` would drop.
+fn oidc_state_cookie(cfg: &Config, value: String, max_age: Duration) -> Cookie<'static> {
+    Cookie::build((OIDC_STATE_COOKIE, value))
+        .path("/")
+        .http_only(true)
+        .secure(cfg.session_cookie_secure)
+        .same_site(SameSite::Lax)
+        .max_age(max_age)
+        .build()
+}
+
+pub fn set_oidc_state_cookie(cookies: &Cookies, cfg: &Config, state: &str, ttl_seconds: u64) {
+    let max_age = Duration::seconds(ttl_seconds as i64);
+    cookies.add(oidc_state_cookie(cfg, state.to_string(), max_age));
+}
+
+/// Reads the OIDC state cookie and removes it; each login can be completed once.
+pub fn take_oidc_state_cookie(cookies: &Cookies, cfg: &Config) -> Option<String> {
+    let value = cookies.get(OIDC_STATE_COOKIE)?.value().to_string();
+    cookies.add(oidc_state_cookie(cfg, String::new(), Duration::ZERO));
+    Some(value)
+}
+
+/// Constant-time comparison of the CSRF header against the cookie value.
+pub fn csrf_matches(cookie: &str, header: &str) -> bool {
+    cookie.len() == header.len()
+        && !cookie.is_empty()
+        && cookie
+            .bytes()
+            .zip(header.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+pub fn parse_same_site(value: &str) -> Option<SameSite> {
+    match value.to_ascii_lowercase().as_str() {
+        "lax" => Some(SameSite::Lax),
+        "strict" => Some(SameSite::Strict),
+        "none" => Some(SameSite::None),
+        _ => None,
+    }
+}
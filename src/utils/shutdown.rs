@@ -0,0 +1,85 @@
+//! Coordinated shutdown: SIGTERM or SIGINT cancels one token that the HTTP
+//! server and every background task watch, and the tasks are tracked so
+//! shutdown can wait for them to finish.
+
+use std::future::Future;
+
+use tokio::time::Instant;
+
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tasks: TaskTracker,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token on the first SIGTERM or SIGINT.
+    pub fn cancel_on_signal(&self) {
+        let token = self.token.clone();
+        tokio::spawn(async move {
+            let signal = signal().await;
+            tracing::info!(signal, "shutdown requested");
+            token.cancel();
+        });
+    }
+
+    /// Resolves once shutdown has been requested.
+    pub async fn requested(&self) {
+        self.token.cancelled().await
+    }
+
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Spawns a background task that shutdown waits for; it should return
+    /// soon after [`Shutdown::requested`] resolves.
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.spawn(task);
+    }
+
+    /// Waits until `deadline` for the spawned tasks; false if some are still running.
+    pub async fn wait_for_tasks(&self, deadline: Instant) -> bool {
+        self.tasks.close();
+        tokio::time::timeout_at(deadline, self.tasks.wait())
+            .await
+            .is_ok()
+    }
+}
+
+async fn signal() -> &'static str {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %e, "failed to listen for SIGINT");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = interrupt => "SIGINT",
+        _ = terminate => "SIGTERM",
+    }
+}
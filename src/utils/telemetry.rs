@@ -0,0 +1,73 @@
+//! Export of `tracing` spans to an OpenTelemetry collector over OTLP/HTTP.
+
+use opentelemetry::{trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{Sampler, Tracer, TracerProvider},
+    Resource,
+};
+
+use crate::config::Config;
+
+/// Keeps the tracer provider alive; [`Telemetry::shutdown`] flushes the
+/// spans still queued for export.
+pub struct Telemetry {
+    provider: Option<TracerProvider>,
+}
+
+impl Telemetry {
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                tracing::warn!(error = %e, "failed to flush pending spans");
+            }
+        }
+    }
+}
+
+/// Builds the OTLP tracer when `OTEL_EXPORTER_OTLP_ENDPOINT` is set, and
+/// installs the W3C trace context propagator used for incoming `traceparent`.
+pub fn init(cfg: &Config) -> anyhow::Result<(Telemetry, Option<Tracer>)> {
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    let Some(endpoint) = &cfg.otlp_endpoint else {
+        return Ok((Telemetry { provider: None }, None));
+    };
+
+    let provider = build_provider(
+        endpoint,
+        cfg.otel_traces_sample_ratio,
+        &cfg.otel_service_name,
+    )?;
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    opentelemetry::global::set_tracer_provider(provider.clone());
+    Ok((
+        Telemetry {
+            provider: Some(provider),
+        },
+        Some(tracer),
+    ))
+}
+
+/// Batching OTLP/HTTP exporter that posts to `<endpoint>/v1/traces`.
+pub(crate) fn build_provider(
+    endpoint: &str,
+    sample_ratio: f64,
+    service_name: &str,
+) -> anyhow::Result<TracerProvider> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            sample_ratio,
+        ))))
+        .with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            service_name.to_string(),
+        )]))
+        .build())
+}
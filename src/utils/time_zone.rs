@@ -0,0 +1,45 @@
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Serialize, Serializer};
+
+/// Request header overriding the user's stored time zone for one request,
+/// e.g. `Accept-Timezone: Europe/Berlin`.
+pub const TIME_ZONE_HEADER: &str = "accept-timezone";
+
+tokio::task_local! {
+    static RESPONSE_ZONE: Tz;
+}
+
+/// Parses an IANA time zone name such as `America/New_York`, ignoring case.
+pub fn parse(name: &str) -> Option<Tz> {
+    Tz::from_str_insensitive(name.trim()).ok()
+}
+
+/// Runs `f` (typically the rest of the request) with timestamps rendered in `zone`.
+pub async fn scope<F: Future>(zone: Tz, f: F) -> F::Output {
+    RESPONSE_ZONE.scope(zone, f).await
+}
+
+/// Zone for the response being built; UTC outside of [`scope`].
+pub fn current() -> Tz {
+    RESPONSE_ZONE.try_with(|zone| *zone).unwrap_or(Tz::UTC)
+}
+
+/// `serialize_with` helper: timestamps are stored in UTC and rendered as
+/// RFC 3339 with the offset of the response zone.
+pub fn serialize<S: Serializer>(dt: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    dt.with_timezone(&current())
+        .fixed_offset()
+        .serialize(serializer)
+}
+
+/// [`serialize`] for optional timestamps.
+pub fn serialize_opt<S: Serializer>(
+    dt: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    dt.map(|dt| dt.with_timezone(&current()).fixed_offset())
+        .serialize(serializer)
+}
@@ -0,0 +1,59 @@
+//! HTTPS termination with rustls. The certificate chain and key are read
+//! from PEM files and reloaded when the files change, so renewed
+//! certificates are picked up without a restart. ALPN offers `h2` before
+//! `http/1.1`, so clients that support it use HTTP/2.
+
+use std::{path::Path, time::Duration, time::SystemTime};
+
+use axum_server::tls_rustls::RustlsConfig;
+
+use crate::{config::TlsConfig, utils::shutdown::Shutdown};
+
+/// Loads the certificate and key, failing on unreadable or mismatched files.
+pub async fn load(cfg: &TlsConfig) -> anyhow::Result<RustlsConfig> {
+    // ring is the only provider compiled in; installing fails if one already is
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(&cfg.cert_path, &cfg.key_path)
+        .await
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "loading TLS certificate {} and key {}: {}",
+                cfg.cert_path.display(),
+                cfg.key_path.display(),
+                e
+            )
+        })
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Background task that polls the certificate and key files and reloads
+/// them when either changes. A failed reload keeps serving the previous
+/// certificate.
+pub fn spawn_reload(shutdown: &Shutdown, tls: RustlsConfig, cfg: TlsConfig) {
+    let stop = shutdown.token();
+    shutdown.spawn(async move {
+        let mut seen = (modified(&cfg.cert_path), modified(&cfg.key_path));
+        let mut interval = tokio::time::interval(Duration::from_secs(cfg.reload_interval_seconds));
+        interval.tick().await;
+        loop {
+            tokio::select! {
+                _ = stop.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            let current = (modified(&cfg.cert_path), modified(&cfg.key_path));
+            if current == seen {
+                continue;
+            }
+            // Not retried until the files change again, e.g. once a renewal
+            // has written both of them
+            seen = current;
+            match tls.reload_from_pem_file(&cfg.cert_path, &cfg.key_path).await {
+                Ok(()) => tracing::info!(cert = %cfg.cert_path.display(), "TLS certificate reloaded"),
+                Err(e) => tracing::error!(error = %e, "TLS certificate reload failed, keeping the previous one"),
+            }
+        }
+    });
+}